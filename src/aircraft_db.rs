@@ -0,0 +1,256 @@
+//! A local aircraft database keyed by icao24, so that aircraft details don't need adsbdb.
+//!
+//! Two formats are read, optionally gzipped:
+//!
+//! - OpenSky's `aircraftDatabase.csv`, comma-separated with a header row and fields quoted with
+//!   `"` or, in newer exports, `'`.
+//! - tar1090-db's `aircraft.csv.gz`, semicolon-separated without a header:
+//!   `icao24;registration;type;flags;description;year;owner/operator`.
+
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AircraftDbConfig {
+    /// The database file, reloaded when it changes.
+    pub path: Option<PathBuf>,
+}
+
+/// What is known about an aircraft. Fields missing from the source are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AircraftRecord {
+    pub registration: Option<String>,
+    /// ICAO type designator, e.g. `A20N`.
+    pub type_code: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub operator: Option<String>,
+    pub owner: Option<String>,
+}
+
+pub struct AircraftDb {
+    path: PathBuf,
+    /// Modification time of the file when it was read, or last tried to be.
+    modified: Option<SystemTime>,
+    records: HashMap<String, AircraftRecord>,
+}
+
+impl AircraftDb {
+    pub fn load(path: &Path) -> Result<AircraftDb, String> {
+        let describe = |e: String| format!("{}: {}", path.display(), e);
+        // Taken before reading, so that a write during the read is picked up next time.
+        let modified = modified(path);
+        let data = std::fs::read(path).map_err(|e| describe(e.to_string()))?;
+        Ok(AircraftDb {
+            path: path.to_path_buf(),
+            modified,
+            records: parse(&data).map_err(describe)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the file has changed since it was read, or last tried to be.
+    pub fn is_stale(&self) -> bool {
+        self.file_modified() != self.modified
+    }
+
+    /// The current modification time of the file.
+    pub fn file_modified(&self) -> Option<SystemTime> {
+        modified(&self.path)
+    }
+
+    /// Keeps the current table after the file modified at `modified` couldn't be read, so that it
+    /// isn't tried again until it changes.
+    pub fn reload_failed(&mut self, modified: Option<SystemTime>) {
+        self.modified = modified;
+    }
+
+    pub fn get(&self, icao24: &str) -> Option<&AircraftRecord> {
+        self.records.get(&icao24.to_ascii_lowercase())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Parses a database in either format, gzipped or not, by icao24 in lower case.
+pub fn parse(data: &[u8]) -> Result<HashMap<String, AircraftRecord>, String> {
+    let mut text = String::new();
+    if data.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(data)
+            .read_to_string(&mut text)
+            .map_err(|e| format!("decompressing: {}", e))?;
+    } else {
+        text = String::from_utf8_lossy(data).into_owned();
+    }
+
+    let first_line = text.lines().next().unwrap_or_default();
+    if first_line.contains("icao24") {
+        parse_opensky(&text, first_line)
+    } else {
+        parse_tar1090(&text)
+    }
+}
+
+fn parse_opensky(text: &str, header: &str) -> Result<HashMap<String, AircraftRecord>, String> {
+    let quote = if header.starts_with('\'') {
+        b'\''
+    } else {
+        b'"'
+    };
+    let mut records = HashMap::new();
+    let mut reader = csv::ReaderBuilder::new()
+        .quote(quote)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    for (i, row) in reader.records().enumerate() {
+        let row = row.map_err(|e| format!("line {}: {}", i + 2, e))?;
+        let field = |name: &str| {
+            let index = headers.iter().position(|h| h == name)?;
+            row.get(index).filter(|v| !v.is_empty()).map(str::to_string)
+        };
+        let Some(icao24) = field("icao24") else {
+            continue;
+        };
+        insert(
+            &mut records,
+            icao24,
+            AircraftRecord {
+                registration: field("registration"),
+                type_code: field("typecode"),
+                manufacturer: field("manufacturername"),
+                model: field("model"),
+                operator: field("operator"),
+                owner: field("owner"),
+            },
+        );
+    }
+    Ok(records)
+}
+
+fn parse_tar1090(text: &str) -> Result<HashMap<String, AircraftRecord>, String> {
+    let mut records = HashMap::new();
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    for (i, row) in reader.records().enumerate() {
+        let row = row.map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let field = |index: usize| {
+            row.get(index)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let Some(icao24) = field(0) else {
+            continue;
+        };
+        // Owner and operator share a column.
+        let owner = field(6);
+        insert(
+            &mut records,
+            icao24,
+            AircraftRecord {
+                registration: field(1),
+                type_code: field(2),
+                manufacturer: None,
+                model: field(4),
+                operator: owner.clone(),
+                owner,
+            },
+        );
+    }
+    Ok(records)
+}
+
+/// Adds `record` unless all its fields are empty, so that such aircraft are looked up in
+/// adsbdb instead.
+fn insert(records: &mut HashMap<String, AircraftRecord>, icao24: String, record: AircraftRecord) {
+    if record != AircraftRecord::default() {
+        records.insert(icao24.to_ascii_lowercase(), record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_opensky() {
+        let csv = "'icao24','registration','manufacturericao','manufacturername','model','typecode','operator','owner'\n\
+                   '4b1805','HB-JCA','AIRBUS','Airbus','A220-300','BCS3','Swiss','Swiss International Air Lines, Ltd.'\n\
+                   '4b1806','','','','','','',''\n";
+        let records = parse(csv.as_bytes()).unwrap();
+        assert_eq!(
+            records["4b1805"],
+            AircraftRecord {
+                registration: Some("HB-JCA".to_string()),
+                type_code: Some("BCS3".to_string()),
+                manufacturer: Some("Airbus".to_string()),
+                model: Some("A220-300".to_string()),
+                operator: Some("Swiss".to_string()),
+                owner: Some("Swiss International Air Lines, Ltd.".to_string()),
+            }
+        );
+        assert!(!records.contains_key("4b1806"));
+
+        let csv = "icao24,registration,typecode\n\"4B1807\",\"HB-JNA\",\"B77W\"\n";
+        let records = parse(csv.as_bytes()).unwrap();
+        assert_eq!(records["4b1807"].type_code.as_deref(), Some("B77W"));
+        assert_eq!(records["4b1807"].owner, None);
+    }
+
+    #[test]
+    fn test_parse_tar1090() {
+        let csv = "4B1805;HB-JCA;BCS3;00;AIRBUS A-220-300;2017;Swiss;\nAE1234;;C130;10;;;;\n4B1806;;;00;;;;\n";
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(csv.as_bytes()).unwrap();
+        let records = parse(&gz.finish().unwrap()).unwrap();
+        assert_eq!(records.len(), 2);
+        let record = &records["4b1805"];
+        assert_eq!(record.registration.as_deref(), Some("HB-JCA"));
+        assert_eq!(record.model.as_deref(), Some("AIRBUS A-220-300"));
+        assert_eq!(record.operator.as_deref(), Some("Swiss"));
+        assert_eq!(records["ae1234"].registration, None);
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = std::env::temp_dir().join(format!("radar-aircraft-{}.csv", std::process::id()));
+        std::fs::write(&path, "4B1805;HB-JCA;BCS3;00;;;;\n").unwrap();
+        let db = AircraftDb::load(&path).unwrap();
+        assert_eq!(db.len(), 1);
+        assert!(db.get("4B1805").is_some());
+        assert!(!db.is_stale());
+
+        let file = std::fs::File::options().append(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert!(db.is_stale());
+        std::fs::remove_file(&path).unwrap();
+        assert!(db.is_stale());
+        assert!(AircraftDb::load(&path).is_err());
+        let mut db = db;
+        db.reload_failed(db.file_modified());
+        assert!(!db.is_stale());
+        assert!(db.get("4b1805").is_some());
+    }
+}
@@ -0,0 +1,142 @@
+//! Airlines, from the ICAO designator that starts the callsign of airline flights.
+//!
+//! A table of common airlines with their IATA code and a brand colour from the panel palette is
+//! bundled. Logos can be added as `<ICAO>.svg` or `<ICAO>.png` files in the configured
+//! `logos_dir`, which are read on every render like layouts, so that new ones show up on the next
+//! request.
+
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const BUNDLED: &str = include_str!("../data/airlines.csv");
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AirlinesConfig {
+    /// Directory of logos named by ICAO designator, e.g. `SWR.svg`.
+    pub logos_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Airline {
+    pub icao: String,
+    pub iata: Option<String>,
+    pub name: String,
+    /// Brand colour, the nearest palette colour as `#RRGGBB`.
+    pub color: String,
+}
+
+pub struct Airlines {
+    by_icao: HashMap<String, Airline>,
+    logos_dir: Option<PathBuf>,
+}
+
+impl Airlines {
+    /// The bundled table, with logos from `config.logos_dir`.
+    pub fn new(config: &AirlinesConfig) -> Airlines {
+        Airlines {
+            by_icao: parse_airlines(BUNDLED).expect("bundled airline table is valid"),
+            logos_dir: config.logos_dir.clone(),
+        }
+    }
+
+    /// The airline flying as `callsign`, if it is an airline callsign of one in the table.
+    pub fn lookup(&self, callsign: &str) -> Option<&Airline> {
+        self.by_icao.get(designator(callsign)?)
+    }
+
+    /// The logo of the airline flying as `callsign` as a data URI, if there is one in the logo
+    /// directory. Airlines missing from the table can have logos too.
+    pub fn logo(&self, callsign: &str) -> Option<String> {
+        let dir = self.logos_dir.as_ref()?;
+        let icao = designator(callsign)?;
+        [("svg", "image/svg+xml"), ("png", "image/png")]
+            .into_iter()
+            .find_map(|(extension, mime)| {
+                let bytes = std::fs::read(dir.join(format!("{}.{}", icao, extension))).ok()?;
+                Some(format!(
+                    "data:{};base64,{}",
+                    mime,
+                    general_purpose::STANDARD.encode(bytes)
+                ))
+            })
+    }
+}
+
+/// The airline designator of an airline callsign, e.g. `SWR` for `SWR123A`: three letters
+/// followed by the flight number. Registrations used as callsigns by private and general
+/// aviation flights, e.g. `HBZZA` or `N123AB`, have none.
+pub fn designator(callsign: &str) -> Option<&str> {
+    let callsign = callsign.trim();
+    let bytes = callsign.as_bytes();
+    if bytes.len() > 3 && bytes[..3].iter().all(u8::is_ascii_uppercase) && bytes[3].is_ascii_digit()
+    {
+        Some(&callsign[..3])
+    } else {
+        None
+    }
+}
+
+fn parse_airlines(data: &str) -> Result<HashMap<String, Airline>, String> {
+    let mut airlines = HashMap::new();
+    for (i, record) in csv::Reader::from_reader(data.as_bytes())
+        .deserialize()
+        .enumerate()
+    {
+        let airline: Airline = record.map_err(|e| format!("airline {}: {}", i + 1, e))?;
+        airlines.insert(airline.icao.clone(), airline);
+    }
+    Ok(airlines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_designator() {
+        assert_eq!(designator("SWR123A"), Some("SWR"));
+        assert_eq!(designator("EZY12AB "), Some("EZY"));
+        assert_eq!(designator("HBZZA"), None);
+        assert_eq!(designator("N123AB"), None);
+        assert_eq!(designator("SWR"), None);
+        assert_eq!(designator("swr123"), None);
+        assert_eq!(designator(""), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("SWR.svg"), "<svg/>").unwrap();
+        let airlines = Airlines::new(&AirlinesConfig {
+            logos_dir: Some(dir.path().to_path_buf()),
+        });
+
+        let swiss = airlines.lookup("SWR8VP").unwrap();
+        assert_eq!(swiss.name, "Swiss");
+        assert_eq!(swiss.iata.as_deref(), Some("LX"));
+        assert_eq!(airlines.lookup("NJE123").unwrap().iata, None);
+        assert!(airlines.lookup("HBZZA").is_none());
+        assert!(airlines.lookup("XXX123").is_none());
+        assert_eq!(
+            airlines.logo("SWR8VP").as_deref(),
+            Some("data:image/svg+xml;base64,PHN2Zy8+")
+        );
+        assert!(airlines.logo("DLH4AB").is_none());
+        assert!(
+            Airlines::new(&AirlinesConfig::default())
+                .logo("SWR8VP")
+                .is_none()
+        );
+
+        // Brand colours are drawn without dithering.
+        let palette = [
+            "#000000", "#FFFFFF", "#FF0000", "#00FF00", "#0000FF", "#FFFF00",
+        ];
+        for airline in airlines.by_icao.values() {
+            assert!(palette.contains(&airline.color.as_str()), "{:?}", airline);
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Local airport and route tables, so that routes can be shown without asking adsbdb.
+//!
+//! Airports are read from OurAirports' `airports.csv` or the airport files of VRS standing data
+//! (`Code,Name,ICAO,IATA,Location,CountryISO2,Latitude,Longitude,...`), routes from the route
+//! files of VRS standing data (`Callsign,Code,Number,AirlineCode,AirportCodes`). Either path can
+//! also be a directory, whose `.csv` files are all read, as standing data is split by letter.
+
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::geo::haversine_distance;
+
+/// Ground speed, in m/s, below which there is no arrival estimate, as the aircraft is taxiing
+/// or the speed is bogus.
+const MIN_ETA_SPEED: f64 = 25.0;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AirportsConfig {
+    /// Airports file or directory.
+    pub airports: Option<PathBuf>,
+    /// Routes file or directory, by callsign. Only used with `airports`.
+    pub routes: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Airport {
+    pub icao: String,
+    pub iata: Option<String>,
+    pub name: String,
+    pub city: Option<String>,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// How far along its route an aircraft is, in great-circle distances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// From the origin to the destination.
+    pub route_km: f64,
+    /// From the origin to the aircraft.
+    pub flown_km: f64,
+    /// From the aircraft to the destination.
+    pub remaining_km: f64,
+    /// `flown_km` as a fraction of `flown_km + remaining_km`, so that aircraft off the direct
+    /// route still go from 0 to 1.
+    pub fraction: f64,
+}
+
+impl Progress {
+    pub fn new(origin: &Airport, destination: &Airport, position: (f64, f64)) -> Progress {
+        let (lat, lon) = position;
+        let flown_km = haversine_distance(origin.latitude, origin.longitude, lat, lon);
+        let remaining_km =
+            haversine_distance(lat, lon, destination.latitude, destination.longitude);
+        let total = flown_km + remaining_km;
+        Progress {
+            route_km: haversine_distance(
+                origin.latitude,
+                origin.longitude,
+                destination.latitude,
+                destination.longitude,
+            ),
+            flown_km,
+            remaining_km,
+            fraction: if total > 0.0 { flown_km / total } else { 0.0 },
+        }
+    }
+
+    /// How long the remaining distance takes at `ground_speed` in m/s, as the crow flies and
+    /// without slowing down for the approach.
+    pub fn time_remaining(&self, ground_speed: f64) -> Option<TimeDelta> {
+        if ground_speed < MIN_ETA_SPEED {
+            return None;
+        }
+        TimeDelta::try_seconds((self.remaining_km * 1000.0 / ground_speed).round() as i64)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Airports {
+    by_icao: HashMap<String, Airport>,
+    /// ICAO code by IATA code.
+    iata_to_icao: HashMap<String, String>,
+    /// Airport codes of each leg's ends by callsign, ICAO or IATA.
+    routes: HashMap<String, Vec<String>>,
+}
+
+impl Airports {
+    /// Reads the configured tables. Without an airports table there is nothing to look up.
+    pub fn load(config: &AirportsConfig) -> Result<Airports, String> {
+        let mut airports = Airports::default();
+        let Some(path) = &config.airports else {
+            return Ok(airports);
+        };
+        for (path, text) in read_csv_files(path)? {
+            airports
+                .add_airports(&text)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        if let Some(path) = &config.routes {
+            for (path, text) in read_csv_files(path)? {
+                airports
+                    .add_routes(&text)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+        }
+        Ok(airports)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_icao.len()
+    }
+
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// The airport with this ICAO or IATA code.
+    pub fn get(&self, code: &str) -> Option<&Airport> {
+        let code = code.trim().to_ascii_uppercase();
+        self.by_icao.get(&code).or_else(|| {
+            self.iata_to_icao
+                .get(&code)
+                .and_then(|icao| self.by_icao.get(icao))
+        })
+    }
+
+    /// The first origin and final destination of the flight with `callsign`, if it is in the
+    /// route table and both airports are known.
+    pub fn route(&self, callsign: &str) -> Option<(&Airport, &Airport)> {
+        let codes = self.routes.get(callsign.trim())?;
+        Some((self.get(codes.first()?)?, self.get(codes.last()?)?))
+    }
+
+    fn add_airports(&mut self, text: &str) -> Result<(), String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+        // OurAirports names columns in lower case, VRS in upper camel case.
+        let ourairports = headers.iter().any(|h| h == "ident");
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let [
+            icao,
+            iata,
+            name,
+            city,
+            country,
+            latitude,
+            longitude,
+            kind,
+            ident,
+            gps_code,
+        ] = if ourairports {
+            [
+                "icao_code",
+                "iata_code",
+                "name",
+                "municipality",
+                "iso_country",
+                "latitude_deg",
+                "longitude_deg",
+                "type",
+                "ident",
+                "gps_code",
+            ]
+        } else {
+            [
+                "ICAO",
+                "IATA",
+                "Name",
+                "Location",
+                "CountryISO2",
+                "Latitude",
+                "Longitude",
+                "",
+                "",
+                "",
+            ]
+        }
+        .map(column);
+
+        for (i, row) in reader.records().enumerate() {
+            let row = row.map_err(|e| format!("line {}: {}", i + 2, e))?;
+            let field = |index: Option<usize>| {
+                row.get(index?)
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+            };
+            if field(kind).as_deref() == Some("closed") {
+                continue;
+            }
+            // Older OurAirports files have no `icao_code`, and the ident is the ICAO code of
+            // airports that have one. Small fields have local idents of the same length, but
+            // no `gps_code`.
+            let Some(code) =
+                field(icao).or_else(|| field(gps_code).and(field(ident)).filter(|c| c.len() == 4))
+            else {
+                continue;
+            };
+            let coordinate = |index| field(index).and_then(|v| v.parse::<f64>().ok());
+            let (Some(lat), Some(lon)) = (coordinate(latitude), coordinate(longitude)) else {
+                warn!("line {}: {} has no coordinates, skipping it", i + 2, code);
+                continue;
+            };
+            let airport = Airport {
+                icao: code.to_ascii_uppercase(),
+                iata: field(iata),
+                name: field(name).unwrap_or_else(|| code.clone()),
+                city: field(city),
+                country: field(country),
+                latitude: lat,
+                longitude: lon,
+            };
+            if let Some(iata) = &airport.iata {
+                self.iata_to_icao
+                    .insert(iata.to_ascii_uppercase(), airport.icao.clone());
+            }
+            self.by_icao.insert(airport.icao.clone(), airport);
+        }
+        Ok(())
+    }
+
+    fn add_routes(&mut self, text: &str) -> Result<(), String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| format!("no {} column", name))
+        };
+        let (callsign, codes) = (column("Callsign")?, column("AirportCodes")?);
+        for (i, row) in reader.records().enumerate() {
+            let row = row.map_err(|e| format!("line {}: {}", i + 2, e))?;
+            let (Some(callsign), Some(codes)) = (row.get(callsign), row.get(codes)) else {
+                continue;
+            };
+            let codes: Vec<String> = codes
+                .split('-')
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(str::to_string)
+                .collect();
+            if codes.len() >= 2 {
+                self.routes.insert(callsign.trim().to_string(), codes);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The contents of `path`, or of the `.csv` files in it if it is a directory, sorted by name.
+fn read_csv_files(path: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+    let read = |path: PathBuf| {
+        std::fs::read_to_string(&path)
+            .map(|text| (path.clone(), text))
+            .map_err(|e| format!("reading {}: {}", path.display(), e))
+    };
+    if !path.is_dir() {
+        return Ok(vec![read(path.to_path_buf())?]);
+    }
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(path)
+        .map_err(|e| format!("reading {}: {}", path.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+        .collect();
+    paths.sort();
+    paths.into_iter().map(read).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OURAIRPORTS: &str = "\"id\",\"ident\",\"type\",\"name\",\"latitude_deg\",\"longitude_deg\",\"elevation_ft\",\"iso_country\",\"municipality\",\"gps_code\",\"icao_code\",\"iata_code\"\n\
+        2434,\"LSZH\",\"large_airport\",\"Zürich Airport\",47.458056,8.548056,1417,\"CH\",\"Zurich\",\"LSZH\",\"LSZH\",\"ZRH\"\n\
+        2429,\"LSGG\",\"large_airport\",\"Geneva Cointrin International Airport\",46.238098,6.10895,1411,\"CH\",\"Geneva\",\"LSGG\",\"\",\"GVA\"\n\
+        1,\"LSXX\",\"closed\",\"Old Field\",47.0,8.0,1000,\"CH\",\"\",\"LSXX\",\"LSXX\",\"\"\n\
+        6523,\"00AA\",\"small_airport\",\"Aero B Ranch Airport\",38.704022,-101.473911,3435,\"US\",\"Leoti\",\"\",\"\",\"\"\n\
+        2,\"LSZB\",\"medium_airport\",\"Bern Airport\",,7.4991,1674,\"CH\",\"Bern\",\"LSZB\",\"LSZB\",\"BRN\"\n";
+
+    const VRS_AIRPORTS: &str = "Code,Name,ICAO,IATA,Location,CountryISO2,Latitude,Longitude,AltitudeFeet\n\
+        EGLL,Heathrow,EGLL,LHR,London,GB,51.4706,-0.461941,83\n";
+
+    const VRS_ROUTES: &str = "Callsign,Code,Number,AirlineCode,AirportCodes\n\
+        SWR8VP,SWR,8VP,SWR,EGLL-LSZH\n\
+        SWR1,SWR,1,SWR,LSGG-LSZH-KJFK\n\
+        SWR2,SWR,2,SWR,LSZH\n";
+
+    fn airports() -> Airports {
+        let mut airports = Airports::default();
+        airports.add_airports(OURAIRPORTS).unwrap();
+        airports.add_airports(VRS_AIRPORTS).unwrap();
+        airports.add_routes(VRS_ROUTES).unwrap();
+        airports
+    }
+
+    #[test]
+    fn test_airports() {
+        let airports = airports();
+        assert_eq!(airports.len(), 3);
+        let zurich = airports.get("ZRH").unwrap();
+        assert_eq!(zurich.icao, "LSZH");
+        assert_eq!(zurich.name, "Zürich Airport");
+        assert_eq!(zurich.city.as_deref(), Some("Zurich"));
+        assert_eq!(zurich.country.as_deref(), Some("CH"));
+        // From the ident.
+        assert_eq!(airports.get("gva").unwrap().icao, "LSGG");
+        assert_eq!(
+            airports.get("EGLL").unwrap().city.as_deref(),
+            Some("London")
+        );
+        assert!(airports.get("LSXX").is_none());
+        // A local ident, not an ICAO code.
+        assert!(airports.get("00AA").is_none());
+        // Without a latitude, skipped rather than failing the whole table.
+        assert!(airports.get("LSZB").is_none());
+
+        let (origin, destination) = airports.route("SWR8VP").unwrap();
+        assert_eq!(
+            (origin.icao.as_str(), destination.icao.as_str()),
+            ("EGLL", "LSZH")
+        );
+        // JFK isn't in the table.
+        assert!(airports.route("SWR1").is_none());
+        // A single airport isn't a route.
+        assert!(airports.route("SWR2").is_none());
+        assert!(airports.route("EZY1").is_none());
+    }
+
+    #[test]
+    fn test_progress() {
+        let airports = airports();
+        let (heathrow, zurich) = (airports.get("LHR").unwrap(), airports.get("ZRH").unwrap());
+        let progress = Progress::new(heathrow, zurich, (47.4, 8.4));
+        assert!((progress.route_km - 788.0).abs() < 5.0, "{:?}", progress);
+        assert!((progress.remaining_km - 12.7).abs() < 0.5, "{:?}", progress);
+        // A few km off the direct route, west of the airport.
+        let detour = progress.flown_km + progress.remaining_km - progress.route_km;
+        assert!(detour > 0.0 && detour < 10.0, "{:?}", progress);
+        assert!(progress.fraction > 0.98 && progress.fraction < 1.0);
+        // 12.9 km at 70 m/s.
+        assert_eq!(progress.time_remaining(70.0), Some(TimeDelta::seconds(184)));
+        assert_eq!(progress.time_remaining(10.0), None);
+        assert_eq!(
+            Progress::new(zurich, zurich, (47.458056, 8.548056)).fraction,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Aircraft worth a second look, highlighted with a red banner and preferred over closer ones.
+//!
+//! Emergency squawks always raise an alert. Rare types, military icao24 blocks and government
+//! callsigns are configurable, with defaults for the usual suspects.
+
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+
+use crate::traffic::Aircraft;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// ICAO type designators, e.g. `A388`.
+    pub rare_types: Vec<String>,
+    /// icao24 address blocks of military aircraft, e.g. `ae0000-afffff`.
+    pub military_ranges: Vec<String>,
+    /// Callsign prefixes of government and air force flights, e.g. `GAF`.
+    pub government_callsigns: Vec<String>,
+    /// How much farther than the closest aircraft one with an alert may be and still be shown
+    /// instead, in km.
+    pub priority_km: f64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        AlertsConfig {
+            rare_types: strings(&[
+                "A388", "A124", "A225", "AN22", "A3ST", "A337", "BLCF", "CONC", "JU52", "DC3",
+                "B17", "P51", "SPIT",
+            ]),
+            // From tar1090's list: the US, the UK, Germany, France and Switzerland.
+            military_ranges: strings(&[
+                "adf7c8-afffff",
+                "43c000-43cfff",
+                "3ea000-3ebfff",
+                "3f4000-3fbfff",
+                "3aa000-3affff",
+                "3b7000-3bffff",
+                "4b7000-4b7fff",
+            ]),
+            government_callsigns: strings(&["SAM", "GAF", "RRR", "CTM", "IAM", "PLF", "SUI"]),
+            priority_km: 2.0,
+        }
+    }
+}
+
+/// Why an aircraft stands out, most urgent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Alert {
+    Hijack,
+    RadioFailure,
+    Emergency,
+    Military,
+    Government,
+    Rare,
+}
+
+impl Alert {
+    /// Name for layouts, e.g. `emergency`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Alert::Hijack => "hijack",
+            Alert::RadioFailure => "radio_failure",
+            Alert::Emergency => "emergency",
+            Alert::Military => "military",
+            Alert::Government => "government",
+            Alert::Rare => "rare",
+        }
+    }
+
+    /// Banner text.
+    pub fn label(self) -> &'static str {
+        match self {
+            Alert::Hijack => "SQUAWK 7500 · HIJACK",
+            Alert::RadioFailure => "SQUAWK 7600 · RADIO FAILURE",
+            Alert::Emergency => "SQUAWK 7700 · EMERGENCY",
+            Alert::Military => "MILITARY",
+            Alert::Government => "GOVERNMENT",
+            Alert::Rare => "RARE AIRCRAFT",
+        }
+    }
+}
+
+pub struct Alerts {
+    rare_types: Vec<String>,
+    military_ranges: Vec<RangeInclusive<u32>>,
+    government_callsigns: Vec<String>,
+    pub priority_km: f64,
+}
+
+impl Alerts {
+    pub fn new(config: &AlertsConfig) -> Result<Alerts, String> {
+        let military_ranges = config
+            .military_ranges
+            .iter()
+            .map(|range| parse_range(range).ok_or_else(|| format!("bad icao24 range '{}'", range)))
+            .collect::<Result<_, _>>()?;
+        if config.priority_km < 0.0 {
+            return Err("alerts priority_km must not be negative".to_string());
+        }
+        Ok(Alerts {
+            rare_types: config
+                .rare_types
+                .iter()
+                .map(|t| t.to_ascii_uppercase())
+                .collect(),
+            military_ranges,
+            government_callsigns: config
+                .government_callsigns
+                .iter()
+                .map(|c| c.to_ascii_uppercase())
+                .collect(),
+            priority_km: config.priority_km,
+        })
+    }
+
+    /// The most urgent alert for `aircraft` of type `type_code`, if any.
+    pub fn check(&self, aircraft: &Aircraft, type_code: Option<&str>) -> Option<Alert> {
+        let squawk = match aircraft.squawk.as_deref().map(str::trim) {
+            Some("7500") => Some(Alert::Hijack),
+            Some("7600") => Some(Alert::RadioFailure),
+            Some("7700") => Some(Alert::Emergency),
+            _ => None,
+        };
+        let address = u32::from_str_radix(&aircraft.icao24, 16).ok();
+        let callsign = aircraft.callsign.trim().to_ascii_uppercase();
+        squawk
+            .or_else(|| {
+                let military =
+                    address.is_some_and(|a| self.military_ranges.iter().any(|r| r.contains(&a)));
+                military.then_some(Alert::Military)
+            })
+            .or_else(|| {
+                let government = !callsign.is_empty()
+                    && self
+                        .government_callsigns
+                        .iter()
+                        .any(|prefix| callsign.starts_with(prefix.as_str()));
+                government.then_some(Alert::Government)
+            })
+            .or_else(|| {
+                let rare = type_code
+                    .is_some_and(|t| self.rare_types.iter().any(|r| r.eq_ignore_ascii_case(t)));
+                rare.then_some(Alert::Rare)
+            })
+    }
+}
+
+/// `from-to` in hex, e.g. `ae0000-afffff`, or a single address.
+fn parse_range(range: &str) -> Option<RangeInclusive<u32>> {
+    let (from, to) = range.split_once('-').unwrap_or((range, range));
+    let from = u32::from_str_radix(from.trim(), 16).ok()?;
+    let to = u32::from_str_radix(to.trim(), 16).ok()?;
+    (from <= to).then_some(from..=to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aircraft(icao24: &str, callsign: &str, squawk: Option<&str>) -> Aircraft {
+        Aircraft {
+            callsign: callsign.to_string(),
+            squawk: squawk.map(str::to_string),
+            ..Aircraft::test(icao24, 3.0)
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let alerts = Alerts::new(&AlertsConfig::default()).unwrap();
+        let check = |a: &Aircraft, t| alerts.check(a, t);
+
+        assert_eq!(
+            check(&aircraft("4b1805", "SWR8VP", Some("1000")), Some("BCS3")),
+            None
+        );
+        assert_eq!(
+            check(&aircraft("4b1805", "SWR8VP", Some("7700")), None),
+            Some(Alert::Emergency)
+        );
+        assert_eq!(
+            check(&aircraft("4b1805", "SWR8VP", Some("7600")), None),
+            Some(Alert::RadioFailure)
+        );
+        // An emergency beats everything else about the aircraft.
+        assert_eq!(
+            check(&aircraft("ae1234", "RCH123", Some("7500")), None),
+            Some(Alert::Hijack)
+        );
+        assert_eq!(
+            check(&aircraft("AE1234", "RCH123", None), None),
+            Some(Alert::Military)
+        );
+        assert_eq!(
+            check(&aircraft("3c6444", "GAF612 ", None), None),
+            Some(Alert::Government)
+        );
+        assert_eq!(
+            check(&aircraft("4b1805", "SWR2", None), Some("a388")),
+            Some(Alert::Rare)
+        );
+        assert_eq!(check(&aircraft("4b1805", "", None), None), None);
+        assert!(Alert::Emergency < Alert::Rare);
+    }
+
+    #[test]
+    fn test_config() {
+        let alerts = Alerts::new(&AlertsConfig {
+            rare_types: vec!["B742".to_string()],
+            military_ranges: vec!["4b7123".to_string()],
+            government_callsigns: Vec::new(),
+            priority_km: 1.0,
+        })
+        .unwrap();
+        assert_eq!(
+            alerts.check(&aircraft("4b7123", "", None), None),
+            Some(Alert::Military)
+        );
+        assert_eq!(
+            alerts.check(&aircraft("4b7124", "GAF1", None), Some("A388")),
+            None
+        );
+        assert_eq!(
+            alerts.check(&aircraft("4b7124", "", None), Some("B742")),
+            Some(Alert::Rare)
+        );
+
+        for range in ["xyz-afffff", "afffff-ae0000", ""] {
+            let config = AlertsConfig {
+                military_ranges: vec![range.to_string()],
+                ..Default::default()
+            };
+            assert!(Alerts::new(&config).is_err(), "{}", range);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! A simplified basemap under the radar inset, from a local GeoJSON file.
+//!
+//! Features are classified by their OpenStreetMap tags (`natural`, `waterway`, `highway`, ...) or
+//! by the `class` or `kind` property of vector tile exports, and drawn in exact palette colours
+//! so that they don't need dithering. Features of any other kind are left out.
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Parks, forests and meadows.
+    Green,
+    /// Lakes, rivers and the sea.
+    Water,
+    /// Airport grounds, runways and taxiways.
+    Airport,
+    Road,
+    /// Motorways and other major roads, drawn wider.
+    MajorRoad,
+}
+
+/// How a class is drawn.
+pub struct Style {
+    /// Fill of areas.
+    pub fill: Option<&'static str>,
+    /// Thin outline of areas.
+    pub outline: Option<&'static str>,
+    /// Colour of lines.
+    pub stroke: &'static str,
+    /// Width of lines in pixels.
+    pub line_width: f64,
+}
+
+impl Class {
+    /// Classes in the order they are drawn, bottom first.
+    pub const ALL: [Class; 5] = [
+        Class::Green,
+        Class::Water,
+        Class::Airport,
+        Class::Road,
+        Class::MajorRoad,
+    ];
+
+    /// The class of a feature with these properties, if it belongs on the basemap.
+    pub fn from_properties(properties: &Map<String, Value>) -> Option<Class> {
+        let value = |key: &str| properties.get(key).and_then(Value::as_str);
+
+        for key in ["class", "kind", "natural", "landuse", "leisure", "aeroway"] {
+            let class = match value(key) {
+                Some("water" | "lake" | "ocean" | "river" | "stream" | "canal" | "riverbank") => {
+                    Class::Water
+                }
+                Some(
+                    "park" | "forest" | "wood" | "grass" | "grassland" | "meadow"
+                    | "nature_reserve",
+                ) => Class::Green,
+                Some("aerodrome" | "airport" | "runway" | "taxiway" | "apron") => Class::Airport,
+                Some("motorway" | "trunk" | "primary" | "major_road" | "highway") => {
+                    Class::MajorRoad
+                }
+                Some(
+                    "secondary" | "tertiary" | "minor_road" | "road" | "residential" | "street",
+                ) => Class::Road,
+                _ => continue,
+            };
+            return Some(class);
+        }
+
+        if value("waterway").is_some() || value("water").is_some() {
+            return Some(Class::Water);
+        }
+        if value("aeroway").is_some() {
+            return Some(Class::Airport);
+        }
+        match value("highway") {
+            Some("motorway" | "motorway_link" | "trunk" | "trunk_link" | "primary") => {
+                Some(Class::MajorRoad)
+            }
+            // Footpaths and tracks would only clutter a map this small.
+            Some("secondary" | "tertiary" | "unclassified" | "residential") => Some(Class::Road),
+            _ => None,
+        }
+    }
+
+    pub fn style(self) -> Style {
+        match self {
+            Class::Green => Style {
+                fill: Some("#00FF00"),
+                outline: None,
+                stroke: "#00FF00",
+                line_width: 2.0,
+            },
+            Class::Water => Style {
+                fill: Some("#0000FF"),
+                outline: None,
+                stroke: "#0000FF",
+                line_width: 3.0,
+            },
+            Class::Airport => Style {
+                fill: Some("#FFFF00"),
+                outline: Some("#000000"),
+                stroke: "#000000",
+                line_width: 6.0,
+            },
+            Class::Road => Style {
+                fill: None,
+                outline: Some("#000000"),
+                stroke: "#000000",
+                line_width: 1.5,
+            },
+            Class::MajorRoad => Style {
+                fill: None,
+                outline: Some("#000000"),
+                stroke: "#000000",
+                line_width: 3.0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn class(properties: Value) -> Option<Class> {
+        Class::from_properties(properties.as_object().unwrap())
+    }
+
+    #[test]
+    fn test_from_properties() {
+        assert_eq!(
+            class(json!({ "natural": "water", "name": "Greifensee" })),
+            Some(Class::Water)
+        );
+        assert_eq!(class(json!({ "waterway": "river" })), Some(Class::Water));
+        assert_eq!(class(json!({ "landuse": "forest" })), Some(Class::Green));
+        assert_eq!(
+            class(json!({ "aeroway": "runway", "ref": "14/32" })),
+            Some(Class::Airport)
+        );
+        assert_eq!(
+            class(json!({ "highway": "motorway" })),
+            Some(Class::MajorRoad)
+        );
+        assert_eq!(
+            class(json!({ "highway": "residential" })),
+            Some(Class::Road)
+        );
+        assert_eq!(class(json!({ "highway": "footway" })), None);
+        assert_eq!(class(json!({ "class": "minor_road" })), Some(Class::Road));
+        assert_eq!(class(json!({ "kind": "ocean" })), Some(Class::Water));
+        assert_eq!(class(json!({ "building": "yes" })), None);
+        assert_eq!(class(json!({})), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! Framed, optionally compressed transfer of panel framebuffers.
+//!
+//! A frame is a 20 byte header followed by the payload. All integers are little-endian.
+//!
+//! | Offset | Size | Field                                                      |
+//! |--------|------|------------------------------------------------------------|
+//! | 0      | 4    | Magic `RDRF`                                               |
+//! | 4      | 1    | Format version, currently 1                                |
+//! | 5      | 1    | Compression: 0 = none, 1 = RLE                             |
+//! | 6      | 1    | Panel id of the profile the framebuffer was packed for     |
+//! | 7      | 1    | Reserved, 0                                                |
+//! | 8      | 4    | Length of the decompressed framebuffer                     |
+//! | 12     | 4    | Length of the payload following the header                 |
+//! | 16     | 4    | CRC-32 (IEEE) of the decompressed framebuffer              |
+//!
+//! The RLE payload is PackBits: a control byte `n` followed by either `n + 1` literal bytes
+//! (`n` < 128), or one byte to repeat `257 - n` times (`n` > 128). `n` = 128 is skipped. Runs
+//! never span more than 128 output bytes, so the payload can be decompressed in fixed-size chunks
+//! as it arrives.
+
+use serde::Deserialize;
+
+pub const MAGIC: &[u8; 4] = b"RDRF";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 20;
+
+/// Content coding a client can list in `Accept-Encoding` to receive an RLE frame.
+pub const RLE_CONTENT_ENCODING: &str = "x-epd-rle";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Framed but stored as is.
+    None,
+    Rle,
+}
+
+impl Compression {
+    /// Name as in requests, e.g. `rle`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Rle => "rle",
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Rle => 1,
+        }
+    }
+}
+
+pub struct Frame {
+    pub panel_id: u8,
+    pub data: Vec<u8>,
+}
+
+pub fn encode_frame(raw: &[u8], compression: Compression, panel_id: u8) -> Vec<u8> {
+    let payload = match compression {
+        Compression::None => raw.to_vec(),
+        Compression::Rle => rle_encode(raw),
+    };
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(MAGIC);
+    frame.push(VERSION);
+    frame.push(compression.id());
+    frame.push(panel_id);
+    frame.push(0);
+    frame.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&crc32fast::hash(raw).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    frame
+}
+
+pub fn is_frame(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn decode_frame(data: &[u8]) -> Result<Frame, String> {
+    if data.len() < HEADER_LEN || !is_frame(data) {
+        return Err("not a framebuffer frame".to_string());
+    }
+    if data[4] != VERSION {
+        return Err(format!("unsupported frame version {}", data[4]));
+    }
+
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let raw_len = u32_at(8);
+    let payload_len = u32_at(12);
+    let crc = u32_at(16) as u32;
+
+    let payload = &data[HEADER_LEN..];
+    if payload.len() != payload_len {
+        return Err(format!(
+            "expected {} payload bytes, got {}",
+            payload_len,
+            payload.len()
+        ));
+    }
+
+    let raw = match data[5] {
+        0 => payload.to_vec(),
+        1 => rle_decode(payload)?,
+        other => return Err(format!("unknown compression {}", other)),
+    };
+    if raw.len() != raw_len {
+        return Err(format!(
+            "expected {} bytes after decompression, got {}",
+            raw_len,
+            raw.len()
+        ));
+    }
+    if crc32fast::hash(&raw) != crc {
+        return Err("CRC mismatch".to_string());
+    }
+
+    Ok(Frame {
+        panel_id: data[6],
+        data: raw,
+    })
+}
+
+fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&b| b == data[i])
+            .count();
+        if run >= 2 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        // Collect literals until the next run of three, which is cheaper to encode as a run.
+        let start = i;
+        while i < data.len() && i - start < 128 {
+            if i + 2 < data.len() && data[i] == data[i + 1] && data[i] == data[i + 2] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+
+    out
+}
+
+fn rle_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        let n = data[i] as usize;
+        i += 1;
+        match n {
+            0..=127 => {
+                let literal = data
+                    .get(i..i + n + 1)
+                    .ok_or("truncated literal run in RLE payload")?;
+                out.extend_from_slice(literal);
+                i += n + 1;
+            }
+            128 => {}
+            _ => {
+                let &b = data.get(i).ok_or("truncated repeat run in RLE payload")?;
+                out.extend(std::iter::repeat_n(b, 257 - n));
+                i += 1;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_round_trip() {
+        let mut data = vec![0x11; 1000];
+        data.extend((0..300).map(|i| (i * 37 % 251) as u8));
+        data.extend([0x35, 0x35, 0x12, 0x12, 0x12, 0x40]);
+        data.extend(vec![0x00; 129]);
+
+        let encoded = rle_encode(&data);
+        assert!(encoded.len() < data.len());
+        assert_eq!(rle_decode(&encoded).unwrap(), data);
+
+        assert_eq!(rle_decode(&rle_encode(&[])).unwrap(), Vec::<u8>::new());
+        assert_eq!(rle_decode(&rle_encode(&[7])).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let raw: Vec<u8> = (0..10_000)
+            .map(|i| if i % 600 < 400 { 0x11 } else { 0x23 })
+            .collect();
+
+        for compression in [Compression::None, Compression::Rle] {
+            let frame = encode_frame(&raw, compression, 4);
+            assert_eq!(&frame[..4], MAGIC);
+            assert_eq!(frame[5], compression.id());
+            let decoded = decode_frame(&frame).unwrap();
+            assert_eq!(decoded.panel_id, 4);
+            assert_eq!(decoded.data, raw);
+        }
+
+        let frame = encode_frame(&raw, Compression::Rle, 1);
+        assert!(frame.len() < raw.len() / 10);
+    }
+
+    #[test]
+    fn test_frame_validation() {
+        let raw = vec![0x12; 100];
+        let mut frame = encode_frame(&raw, Compression::None, 1);
+        frame[HEADER_LEN + 10] = 0x13;
+        assert_eq!(decode_frame(&frame).err().unwrap(), "CRC mismatch");
+
+        let frame = encode_frame(&raw, Compression::Rle, 1);
+        assert!(decode_frame(&frame[..frame.len() - 1]).is_err());
+        assert!(decode_frame(&raw).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Optional TOML configuration, read from `RADAR_CONFIG` (default `radar.toml`).
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::aircraft_db::AircraftDbConfig;
+use crate::airlines::AirlinesConfig;
+use crate::airports::AirportsConfig;
+use crate::alerts::{Alerts, AlertsConfig};
+use crate::layouts::{self, DEFAULT_LAYOUT};
+use crate::map::MapConfig;
+use crate::movement::HomeAirportConfig;
+use crate::notify::NotificationsConfig;
+use crate::panel::{self, LayoutOverrides};
+use crate::refresh::RefreshConfig;
+use crate::runways::RunwayConfig;
+use crate::schedule::ScheduleWindow;
+use crate::screens::IdleConfig;
+use crate::units::UnitsConfig;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Panel profile used when a request doesn't select one with `?panel=`.
+    pub panel: String,
+    /// Orientation and strip layout overriding the panel profile's, e.g. for a frame mounted
+    /// upside down.
+    pub panel_layout: LayoutOverrides,
+    /// Where the display is, the centre of the area searched for aircraft.
+    pub observer: ObserverConfig,
+    /// The airport aircraft are classified as arriving at, departing from or flying over.
+    pub home_airport: Option<HomeAirportConfig>,
+    /// Runway thresholds, for working out which runways are in use.
+    pub runways: Vec<RunwayConfig>,
+    /// Time zone for everything that depends on the local time of day.
+    pub timezone: chrono_tz::Tz,
+    pub refresh: RefreshConfig,
+    /// Time-of-day windows replacing live traffic with another screen.
+    pub schedule: Vec<ScheduleWindow>,
+    pub idle: IdleConfig,
+    /// Units for altitudes and speeds on the flight screen.
+    pub units: UnitsConfig,
+    /// The radar inset.
+    pub map: MapConfig,
+    /// Airline logos.
+    pub airlines: AirlinesConfig,
+    /// Local aircraft details, used before adsbdb.
+    pub aircraft_db: AircraftDbConfig,
+    /// Local airport and route tables, used before adsbdb.
+    pub airports: AirportsConfig,
+    /// Which aircraft get an alert banner and are preferred over closer ones.
+    pub alerts: AlertsConfig,
+    /// Where aircraft with an alert are announced.
+    pub notifications: NotificationsConfig,
+    /// Layout of the flight screen when neither the request nor the device selects one.
+    pub layout: String,
+    /// Directory of additional layouts, `<name>.svg`, replacing bundled ones of the same name.
+    pub layouts_dir: Option<PathBuf>,
+    /// Settings per device, by the `?device=` id it sends.
+    pub devices: HashMap<String, DeviceConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObserverConfig {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl ObserverConfig {
+    pub fn position(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
+}
+
+impl Default for ObserverConfig {
+    fn default() -> Self {
+        ObserverConfig {
+            latitude: 47.41876326848794,
+            longitude: 8.426291132310645,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub layout: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            panel: panel::DEFAULT_PANEL.to_string(),
+            panel_layout: LayoutOverrides::default(),
+            observer: ObserverConfig::default(),
+            home_airport: None,
+            runways: Vec::new(),
+            timezone: chrono_tz::Europe::Zurich,
+            refresh: RefreshConfig::default(),
+            schedule: Vec::new(),
+            idle: IdleConfig::default(),
+            units: UnitsConfig::default(),
+            map: MapConfig::default(),
+            airlines: AirlinesConfig::default(),
+            aircraft_db: AircraftDbConfig::default(),
+            airports: AirportsConfig::default(),
+            alerts: AlertsConfig::default(),
+            notifications: NotificationsConfig::default(),
+            layout: DEFAULT_LAYOUT.to_string(),
+            layouts_dir: None,
+            devices: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, Box<dyn std::error::Error>> {
+        let path = std::env::var("RADAR_CONFIG").ok().map(PathBuf::from);
+        let explicit = path.is_some();
+        let path = path.unwrap_or_else(|| PathBuf::from("radar.toml"));
+
+        if !explicit && !path.exists() {
+            info!("No config file found, using defaults.");
+            return Ok(Config::default());
+        }
+
+        info!("Loading config from {}", path.display());
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("reading {}: {}", path.display(), e))?;
+        let config = Config::parse(&text)?;
+        Ok(config)
+    }
+
+    fn parse(text: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let config: Config = toml::from_str(text)?;
+        if panel::find_profile(&config.panel).is_none() {
+            return Err(format!(
+                "unknown panel '{}', expected one of: {}",
+                config.panel,
+                panel::profile_names().join(", ")
+            )
+            .into());
+        }
+        let device_layouts = config.devices.values().filter_map(|d| d.layout.as_ref());
+        for layout in std::iter::once(&config.layout).chain(device_layouts) {
+            layouts::check_layout(config.layouts_dir.as_deref(), layout)?;
+        }
+        if !(-90.0..=90.0).contains(&config.observer.latitude)
+            || !(-180.0..=180.0).contains(&config.observer.longitude)
+        {
+            return Err("observer latitude or longitude out of range".into());
+        }
+        if let Some(home) = &config.home_airport
+            && (!(-90.0..=90.0).contains(&home.latitude)
+                || !(-180.0..=180.0).contains(&home.longitude))
+        {
+            return Err("home_airport latitude or longitude out of range".into());
+        }
+        if let Some(runway) = config.runways.iter().find(|r| {
+            !(-90.0..=90.0).contains(&r.latitude)
+                || !(-180.0..=180.0).contains(&r.longitude)
+                || !(0.0..=360.0).contains(&r.heading)
+        }) {
+            return Err(format!("runway {} position or heading out of range", runway.name).into());
+        }
+        // Negated, so NaN is rejected too.
+        #[allow(clippy::neg_cmp_op_on_partial_ord)]
+        if !(config.map.range_km > 0.0) {
+            return Err("map range_km must be positive".into());
+        }
+        if config.airports.routes.is_some() && config.airports.airports.is_none() {
+            return Err("airports routes need an airports table".into());
+        }
+        Alerts::new(&config.alerts)?;
+        if let Some(url) = (config.notifications.webhooks.iter())
+            .find(|url| !url.starts_with("http://") && !url.starts_with("https://"))
+        {
+            return Err(format!("webhook '{}' must be an http:// or https:// URL", url).into());
+        }
+        if let Some(mqtt) = &config.notifications.mqtt
+            && (mqtt.host.is_empty() || mqtt.topic.is_empty())
+        {
+            return Err("notifications mqtt needs a host and a topic".into());
+        }
+        if let Some(window) = config.schedule.iter().find(|w| w.start == w.end) {
+            return Err(format!(
+                "schedule window starting at {} must end at a different time",
+                window.start.format("%H:%M")
+            )
+            .into());
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panel::Rotation;
+    use crate::schedule::DisplayMode;
+    use crate::units::{AltitudeUnit, SpeedUnit};
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(Config::parse("").unwrap().panel, panel::DEFAULT_PANEL);
+        assert_eq!(
+            Config::parse("panel = 'mono-7in5'").unwrap().panel,
+            "mono-7in5"
+        );
+        assert!(Config::parse("panel = 'nope'").is_err());
+
+        let config = Config::parse("[panel_layout]\nrotation = 270").unwrap();
+        assert_eq!(config.panel_layout.rotation, Some(Rotation::Deg270));
+        assert_eq!(config.panel_layout.strips, None);
+
+        let config =
+            Config::parse("timezone = 'Europe/London'\n[refresh]\nidle_seconds = 600").unwrap();
+        assert_eq!(config.timezone, chrono_tz::Europe::London);
+        assert_eq!(config.refresh.idle_seconds, 600);
+        assert_eq!(config.refresh.active_seconds, 60);
+        assert!(Config::parse("timezone = 'Mars/Olympus'").is_err());
+
+        let config = Config::parse(
+            "[[schedule]]\nstart = '22:00'\nend = '00:00'\nmode = 'summary'\n\n[[schedule]]\nstart = '00:00'\nend = '06:00'\nmode = 'night'",
+        )
+        .unwrap();
+        assert_eq!(config.schedule.len(), 2);
+        assert_eq!(config.schedule[1].mode, DisplayMode::Night);
+        assert!(
+            Config::parse("[[schedule]]\nstart = '7am'\nend = '08:00'\nmode = 'hold'").is_err()
+        );
+        assert!(
+            Config::parse("[[schedule]]\nstart = '07:00'\nend = '07:00'\nmode = 'hold'").is_err()
+        );
+
+        let config =
+            Config::parse("layout = 'text-only'\n[devices.kitchen]\nlayout = 'map-centric'")
+                .unwrap();
+        assert_eq!(config.layout, "text-only");
+        assert_eq!(
+            config.devices["kitchen"].layout.as_deref(),
+            Some("map-centric")
+        );
+        assert!(Config::parse("[devices.kitchen]\nlayout = 'nope'").is_err());
+
+        let config = Config::parse("[units]\nspeed = 'km/h'").unwrap();
+        assert_eq!(config.units.altitude, AltitudeUnit::Feet);
+        assert_eq!(config.units.speed, SpeedUnit::KilometersPerHour);
+        assert!(Config::parse("[units]\naltitude = 'furlongs'").is_err());
+
+        let config = Config::parse(
+            "[observer]\nlatitude = 51.47\nlongitude = -0.45\n[map]\ninset = true\nlandmarks = 'runways.geojson'\nbasemap = 'area.geojson'\ntrail_minutes = 10",
+        )
+        .unwrap();
+        assert_eq!(config.observer.position(), (51.47, -0.45));
+        assert!(config.map.inset);
+        assert_eq!(config.map.range_km, 8.0);
+        assert_eq!(config.map.trail_minutes, 10);
+        assert_eq!(
+            config.map.basemap.as_deref(),
+            Some(std::path::Path::new("area.geojson"))
+        );
+        assert!(Config::parse("[observer]\nlatitude = 51.47").is_err());
+        assert!(Config::parse("[observer]\nlatitude = 91\nlongitude = 0").is_err());
+        assert!(Config::parse("").unwrap().home_airport.is_none());
+        let config = Config::parse(
+            "[home_airport]\nicao = 'LSZH'\niata = 'ZRH'\nlatitude = 47.458\nlongitude = 8.548",
+        )
+        .unwrap();
+        assert_eq!(config.home_airport.unwrap().icao, "LSZH");
+        assert!(Config::parse("[home_airport]\nicao = 'LSZH'").is_err());
+        let config = Config::parse(
+            "[idle]\nshow_runways = true\n[[runways]]\nname = '14'\nlatitude = 47.4823\nlongitude = 8.5368\nheading = 137\nelevation = 422",
+        )
+        .unwrap();
+        assert!(config.idle.show_runways);
+        assert_eq!(config.runways[0].name, "14");
+        assert!(
+            Config::parse(
+                "[[runways]]\nname = '14'\nlatitude = 47.4\nlongitude = 8.5\nheading = 400"
+            )
+            .is_err()
+        );
+        assert!(Config::parse("[map]\nrange_km = 0").is_err());
+        assert!(Config::parse("[map]\nrange_km = nan").is_err());
+
+        let config = Config::parse(
+            "[airlines]\nlogos_dir = 'logos'\n[aircraft_db]\npath = 'aircraft.csv.gz'",
+        )
+        .unwrap();
+        assert_eq!(
+            config.airlines.logos_dir.as_deref(),
+            Some(std::path::Path::new("logos"))
+        );
+        assert_eq!(
+            config.aircraft_db.path.as_deref(),
+            Some(std::path::Path::new("aircraft.csv.gz"))
+        );
+        assert!(Config::parse("[airports]\nroutes = 'routes'").is_err());
+        assert!(Config::parse("[airports]\nairports = 'airports.csv'\nroutes = 'routes'").is_ok());
+
+        let config = Config::parse("[alerts]\nrare_types = ['B742']\npriority_km = 3").unwrap();
+        assert_eq!(config.alerts.rare_types, ["B742"]);
+        assert!(!config.alerts.military_ranges.is_empty());
+        assert!(Config::parse("[alerts]\nmilitary_ranges = ['ae0000']").is_ok());
+        assert!(Config::parse("[alerts]\nmilitary_ranges = ['military']").is_err());
+
+        let config = Config::parse(
+            "[notifications]\nwebhooks = ['http://localhost:8080/radar']\n[notifications.mqtt]\nhost = 'broker.local'",
+        )
+        .unwrap();
+        let mqtt = config.notifications.mqtt.unwrap();
+        assert_eq!((mqtt.host.as_str(), mqtt.port), ("broker.local", 1883));
+        assert_eq!(mqtt.topic, "radar/alerts");
+        assert!(Config::parse("[notifications]\nwebhooks = ['localhost:8080']").is_err());
+        assert!(Config::parse("[notifications.mqtt]\ntopic = ''").is_err());
+    }
+}
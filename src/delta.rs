@@ -0,0 +1,204 @@
+//! Partial-refresh updates: the rectangles of the panel that changed since the previous frame.
+//!
+//! A delta is an 8 byte header followed by the changed rectangles. All integers are
+//! little-endian and all coordinates are in the panel's native orientation, before the
+//! framebuffer is split into strips.
+//!
+//! | Offset | Size | Field                             |
+//! |--------|------|-----------------------------------|
+//! | 0      | 4    | Magic `RDRD`                      |
+//! | 4      | 1    | Format version, currently 1       |
+//! | 5      | 1    | Panel id                          |
+//! | 6      | 2    | Number of rectangles              |
+//!
+//! Each rectangle is `x`, `y`, `width` and `height` as `u16`, followed by its pixels row by row,
+//! packed like the full framebuffer: most significant bits first, each row padded to whole bytes.
+//! `x` and `width` are multiples of 8 (or reach the right edge), so rows start on a byte boundary
+//! of the full framebuffer for every bit depth.
+
+use crate::panel::{PanelProfile, pack_rect};
+
+pub const MAGIC: &[u8; 4] = b"RDRD";
+pub const VERSION: u8 = 1;
+
+/// Changes are tracked in tiles of this many pixels square.
+const TILE: usize = 8;
+
+/// A native panel rectangle as (`x`, `y`, `width`, `height`).
+pub type Rect = (usize, usize, usize, usize);
+
+/// Encodes the update from `previous` to `current`, both from `native_indices`. Without a
+/// previous frame the whole panel is sent as one rectangle.
+pub fn encode_delta(previous: Option<&[u8]>, current: &[u8], profile: &PanelProfile) -> Vec<u8> {
+    let rects = match previous {
+        Some(previous) if previous.len() == current.len() => {
+            changed_rects(previous, current, profile.width, profile.height)
+        }
+        _ => vec![(0, 0, profile.width, profile.height)],
+    };
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.push(profile.id);
+    out.extend_from_slice(&(rects.len() as u16).to_le_bytes());
+    for rect in rects {
+        let (x, y, w, h) = rect;
+        for v in [x, y, w, h] {
+            out.extend_from_slice(&(v as u16).to_le_bytes());
+        }
+        pack_rect(current, profile, rect, &mut out);
+    }
+    out
+}
+
+/// Covers the changed tiles with rectangles: runs of changed tiles in each tile row, merged with
+/// identical runs in the rows below.
+fn changed_rects(previous: &[u8], current: &[u8], width: usize, height: usize) -> Vec<Rect> {
+    let tiles_x = width.div_ceil(TILE);
+    let tiles_y = height.div_ceil(TILE);
+
+    let tile_changed = |tx: usize, ty: usize| {
+        let (x0, x1) = (tx * TILE, ((tx + 1) * TILE).min(width));
+        (ty * TILE..((ty + 1) * TILE).min(height)).any(|y| {
+            let row = y * width;
+            previous[row + x0..row + x1] != current[row + x0..row + x1]
+        })
+    };
+
+    let mut rects: Vec<Rect> = Vec::new();
+    // Rectangles that reach the previous tile row, as indices into `rects`.
+    let mut open: Vec<usize> = Vec::new();
+
+    for ty in 0..tiles_y {
+        let mut spans = Vec::new();
+        let mut tx = 0;
+        while tx < tiles_x {
+            if !tile_changed(tx, ty) {
+                tx += 1;
+                continue;
+            }
+            let start = tx;
+            while tx < tiles_x && tile_changed(tx, ty) {
+                tx += 1;
+            }
+            spans.push((start * TILE, (tx * TILE).min(width)));
+        }
+
+        let y = ty * TILE;
+        let h = TILE.min(height - y);
+        let mut still_open = Vec::new();
+        for (x0, x1) in spans {
+            let extended = open.iter().copied().find(|&i| {
+                let (rx, _, rw, _) = rects[i];
+                (rx, rx + rw) == (x0, x1)
+            });
+            match extended {
+                Some(i) => {
+                    rects[i].3 += h;
+                    still_open.push(i);
+                }
+                None => {
+                    rects.push((x0, y, x1 - x0, h));
+                    still_open.push(rects.len() - 1);
+                }
+            }
+        }
+        open = still_open;
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panel::{self, find_profile};
+
+    /// Applies a delta to `indices`, the way the firmware would.
+    fn apply_delta(delta: &[u8], indices: &mut [u8], profile: &PanelProfile) -> Vec<Rect> {
+        assert_eq!(&delta[..4], MAGIC);
+        assert_eq!(delta[5], profile.id);
+        let u16_at = |pos: usize| u16::from_le_bytes([delta[pos], delta[pos + 1]]) as usize;
+        let bpp = profile.bits_per_pixel as usize;
+        let mask = ((1u16 << bpp) - 1) as u8;
+
+        let mut rects = Vec::new();
+        let mut pos = 8;
+        for _ in 0..u16_at(6) {
+            let (x, y, w, h) = (
+                u16_at(pos),
+                u16_at(pos + 2),
+                u16_at(pos + 4),
+                u16_at(pos + 6),
+            );
+            pos += 8;
+            let row_bytes = profile.row_bytes(w);
+            for row in 0..h {
+                for col in 0..w {
+                    let bit = col * bpp;
+                    let byte = delta[pos + row * row_bytes + bit / 8];
+                    indices[(y + row) * profile.width + x + col] =
+                        (byte >> (8 - bpp - bit % 8)) & mask;
+                }
+            }
+            pos += row_bytes * h;
+            rects.push((x, y, w, h));
+        }
+        assert_eq!(pos, delta.len());
+        rects
+    }
+
+    #[test]
+    fn test_unchanged_frame_has_no_rects() {
+        let profile = find_profile("mono-7in5").unwrap();
+        let frame = vec![1u8; profile.width * profile.height];
+        let delta = encode_delta(Some(&frame), &frame, profile);
+        assert_eq!(delta.len(), 8);
+        assert_eq!(u16::from_le_bytes([delta[6], delta[7]]), 0);
+    }
+
+    #[test]
+    fn test_first_frame_is_full() {
+        let profile = find_profile("bwr-7in5").unwrap();
+        let frame: Vec<u8> = (0..profile.width * profile.height)
+            .map(|i| (i % 3) as u8)
+            .collect();
+        let delta = encode_delta(None, &frame, profile);
+
+        let mut applied = vec![0u8; frame.len()];
+        let rects = apply_delta(&delta, &mut applied, profile);
+        assert_eq!(rects, vec![(0, 0, profile.width, profile.height)]);
+        assert_eq!(applied, frame);
+        // The full rectangle is packed exactly like the framebuffer.
+        assert_eq!(&delta[16..], panel::indices_to_epd_bin(&frame, profile));
+    }
+
+    #[test]
+    fn test_footer_change() {
+        for profile in panel::PROFILES {
+            let (w, h) = (profile.width, profile.height);
+            let previous = vec![1u8; w * h];
+            let mut current = previous.clone();
+            // Two separate changes near the bottom, one spanning a tile boundary.
+            for y in h - 20..h - 5 {
+                for x in 30..45 {
+                    current[y * w + x] = 0;
+                }
+            }
+            current[(h - 3) * w + w - 1] = 0;
+
+            let delta = encode_delta(Some(&previous), &current, profile);
+            let mut applied = previous.clone();
+            let rects = apply_delta(&delta, &mut applied, profile);
+            assert_eq!(applied, current, "{}", profile.name);
+
+            assert_eq!(rects.len(), 2, "{}: {:?}", profile.name, rects);
+            for &(x, y, rw, rh) in &rects {
+                assert!(y >= h - 24 && y + rh <= h, "{:?}", rects);
+                assert!(x % TILE == 0 && (rw % TILE == 0 || x + rw == w));
+            }
+            assert!(delta.len() < profile.buffer_len() / 20);
+        }
+    }
+}
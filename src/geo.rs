@@ -0,0 +1,75 @@
+//! Distances and directions on the earth's surface.
+
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let r = EARTH_RADIUS_KM;
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    r * c
+}
+
+/// Initial bearing from the first point to the second, in degrees clockwise from north.
+pub fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lon = (lon2 - lon1).to_radians();
+    let y = d_lon.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// The nearest of the eight compass points, e.g. `NE` for 40°.
+pub fn compass_point(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((bearing.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+/// Position of (`lat`, `lon`) relative to (`lat0`, `lon0`) in km east and north, on a flat
+/// projection that is accurate enough within a few tens of km.
+pub fn local_offset_km(lat0: f64, lon0: f64, lat: f64, lon: f64) -> (f64, f64) {
+    let km_per_degree = EARTH_RADIUS_KM.to_radians();
+    let east = (lon - lon0) * km_per_degree * lat0.to_radians().cos();
+    let north = (lat - lat0) * km_per_degree;
+    (east, north)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine_distance() {
+        // Distance between two points in Zurich
+        let lat1 = 47.3769;
+        let lon1 = 8.5417;
+        let lat2 = 47.3780;
+        let lon2 = 8.5400;
+        let dist = haversine_distance(lat1, lon1, lat2, lon2);
+        // Approx 0.17 km
+        assert!(dist > 0.1 && dist < 0.3);
+    }
+
+    #[test]
+    fn test_bearing() {
+        assert!((bearing(47.4, 8.4, 47.5, 8.4) - 0.0).abs() < 0.01);
+        assert!((bearing(47.4, 8.4, 47.4, 8.5) - 90.0).abs() < 0.1);
+        assert!((bearing(47.4, 8.4, 47.3, 8.4) - 180.0).abs() < 0.01);
+        assert!((bearing(47.4, 8.4, 47.45, 8.33) - 316.0).abs() < 1.0);
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(40.0), "NE");
+        assert_eq!(compass_point(200.0), "S");
+        assert_eq!(compass_point(350.0), "N");
+    }
+
+    #[test]
+    fn test_local_offset_km() {
+        let (east, north) = local_offset_km(47.4, 8.4, 47.45, 8.5);
+        assert!((north - 5.56).abs() < 0.05);
+        assert!((east - 7.52).abs() < 0.05);
+        let exact = haversine_distance(47.4, 8.4, 47.45, 8.5);
+        assert!((east.hypot(north) - exact).abs() < 0.05);
+    }
+}
@@ -0,0 +1,115 @@
+//! Aircraft that passed through range recently, for the summary screens.
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::traffic::Aircraft;
+
+/// A sighting ends once the aircraft hasn't been seen in range for this long.
+const SIGHTING_GAP: TimeDelta = TimeDelta::minutes(30);
+/// Sightings older than this are forgotten.
+const RETENTION: TimeDelta = TimeDelta::days(2);
+
+#[derive(Debug, Clone)]
+pub struct Sighting {
+    pub icao24: String,
+    pub callsign: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Sighting {
+    /// The callsign, or the transponder address for aircraft that don't send one.
+    pub fn display_name(&self) -> &str {
+        if self.callsign.is_empty() {
+            &self.icao24
+        } else {
+            &self.callsign
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first.
+    sightings: Vec<Sighting>,
+}
+
+impl History {
+    /// Records the aircraft in range at `now`. An aircraft seen again shortly after it was last
+    /// seen extends its sighting instead of counting as a new one.
+    pub fn record<'a>(
+        &mut self,
+        in_range: impl IntoIterator<Item = &'a Aircraft>,
+        now: DateTime<Utc>,
+    ) {
+        for aircraft in in_range {
+            let ongoing = self
+                .sightings
+                .iter_mut()
+                .rev()
+                .find(|s| s.icao24 == aircraft.icao24 && now - s.last_seen < SIGHTING_GAP);
+            match ongoing {
+                Some(sighting) => {
+                    sighting.last_seen = now;
+                    if sighting.callsign.is_empty() {
+                        sighting.callsign = aircraft.callsign.clone();
+                    }
+                }
+                None => self.sightings.push(Sighting {
+                    icao24: aircraft.icao24.clone(),
+                    callsign: aircraft.callsign.clone(),
+                    first_seen: now,
+                    last_seen: now,
+                }),
+            }
+        }
+        self.sightings.retain(|s| now - s.last_seen < RETENTION);
+    }
+
+    /// The sighting with the aircraft seen most recently.
+    pub fn last(&self) -> Option<&Sighting> {
+        self.sightings.iter().max_by_key(|s| s.last_seen)
+    }
+
+    /// Sightings that started at or after `since`, oldest first.
+    pub fn since(&self, since: DateTime<Utc>) -> impl Iterator<Item = &Sighting> {
+        self.sightings.iter().filter(move |s| s.first_seen >= since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aircraft(icao24: &str, callsign: &str) -> Aircraft {
+        Aircraft {
+            callsign: callsign.to_string(),
+            ..Aircraft::test(icao24, 1.0)
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let start = DateTime::parse_from_rfc3339("2026-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let minutes = |m| start + TimeDelta::minutes(m);
+        let swiss = aircraft("4b1805", "SWR123");
+        let easy = aircraft("4b1806", "");
+
+        let mut history = History::default();
+        history.record([&swiss, &easy], start);
+        history.record([&swiss, &aircraft("4b1806", "EZS45")], minutes(1));
+        // Back after a long gap: a second pass.
+        history.record([&swiss], minutes(90));
+
+        let sightings: Vec<_> = history.since(start).collect();
+        assert_eq!(sightings.len(), 3);
+        assert_eq!(sightings[0].last_seen, minutes(1));
+        assert_eq!(sightings[1].callsign, "EZS45");
+        assert_eq!(history.since(minutes(30)).count(), 1);
+
+        history.record([], minutes(60) + TimeDelta::days(2));
+        assert_eq!(history.since(start).count(), 1);
+    }
+}
@@ -0,0 +1,664 @@
+//! Layouts of the flight screen, written as MiniJinja templates.
+//!
+//! A layout defines two blocks: `background` for the photo layer and `overlay` for the text and
+//! vector graphics drawn on top of it. Interpolated values are XML-escaped, see `svg::escape`. The
+//! bundled layouts can be replaced, and new ones added, by `<name>.svg` files in the configured
+//! `layouts_dir`, which are read on every render so that edits show up on the next request.
+//! Templates can call `fit(text, width, size)` to shrink and then ellipsize text that would
+//! overflow its box, and `map_inset(cx, cy, radius)` for the radar inset.
+
+use minijinja::value::Kwargs;
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::airlines::designator;
+use crate::alerts::Alert;
+use crate::geo::compass_point;
+use crate::map::Inset;
+use crate::movement::Movement;
+use crate::svg::{SvgWriter, escape};
+use crate::text_fit::TextFitter;
+use crate::units::UnitsConfig;
+use crate::{Flight, MAX_DISTANCE_KM, SvgLayers};
+
+pub const DEFAULT_LAYOUT: &str = "photo-hero";
+
+/// Size of `country_badge`.
+const COUNTRY_BADGE_WIDTH: f64 = 80.0;
+const COUNTRY_BADGE_HEIGHT: f64 = 48.0;
+
+const BUNDLED: &[(&str, &str)] = &[
+    ("photo-hero", include_str!("../layouts/photo-hero.svg")),
+    ("map-centric", include_str!("../layouts/map-centric.svg")),
+    ("text-only", include_str!("../layouts/text-only.svg")),
+];
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The names of the bundled layouts and those in `dir`, sorted.
+pub fn layout_names(dir: Option<&Path>) -> Vec<String> {
+    let mut names: Vec<String> = BUNDLED.iter().map(|(name, _)| name.to_string()).collect();
+    if let Some(entries) = dir.and_then(|dir| std::fs::read_dir(dir).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "svg")
+                && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+                && is_valid_name(name)
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// The template of the layout called `name`, from `dir` if it has one.
+fn layout_source(dir: Option<&Path>, name: &str) -> Result<Cow<'static, str>, String> {
+    if !is_valid_name(name) {
+        return Err(format!("invalid layout name '{}'", name));
+    }
+    if let Some(dir) = dir {
+        let path = dir.join(format!("{}.svg", name));
+        if path.exists() {
+            return std::fs::read_to_string(&path)
+                .map(Cow::Owned)
+                .map_err(|e| format!("reading {}: {}", path.display(), e));
+        }
+    }
+    BUNDLED
+        .iter()
+        .find(|(bundled, _)| *bundled == name)
+        .map(|(_, source)| Cow::Borrowed(*source))
+        .ok_or_else(|| {
+            format!(
+                "unknown layout '{}', expected one of: {}",
+                name,
+                layout_names(dir).join(", ")
+            )
+        })
+}
+
+/// Checks that the layout exists, without rendering it.
+pub fn check_layout(dir: Option<&Path>, name: &str) -> Result<(), String> {
+    layout_source(dir, name).map(|_| ())
+}
+
+/// The telemetry row: labelled values in `units`, leaving out those the state vector lacks.
+fn telemetry(flight: &Flight, units: &UnitsConfig) -> Vec<Value> {
+    let altitude = flight.baro_altitude.or(flight.geo_altitude);
+    let items = [
+        ("ALTITUDE", altitude.map(|m| units.format_altitude(m))),
+        ("SPEED", flight.ground_speed.map(|v| units.format_speed(v))),
+        ("TRACK", flight.track.map(|t| format!("{:03.0}°", t))),
+        (
+            "VERTICAL",
+            flight.vertical_rate.map(|v| units.format_vertical_rate(v)),
+        ),
+        (
+            "DISTANCE",
+            Some(format!(
+                "{:.1} km {}",
+                flight.distance,
+                compass_point(flight.bearing)
+            )),
+        ),
+        ("SQUAWK", flight.squawk.clone()),
+    ];
+    items
+        .into_iter()
+        .filter_map(|(label, value)| Some(context! { label, value => value? }))
+        .collect()
+}
+
+/// The variables available to layouts.
+fn flight_context(flight: &Flight, units: &UnitsConfig) -> Value {
+    let progress = flight.progress();
+
+    // Position on a map centred on the observer, as a fraction of the display radius with x
+    // to the east and y to the south, like SVG coordinates.
+    let scale = flight.distance / MAX_DISTANCE_KM;
+    let bearing = flight.bearing.to_radians();
+
+    context! {
+        icao24 => flight.icao24,
+        callsign => flight.callsign,
+        flight_number => flight.flight_number,
+        aircraft_type => flight.aircraft_type,
+        registration => flight.registration,
+        type_code => flight.type_code,
+        manufacturer => flight.manufacturer,
+        operator => flight.operator,
+        owner => flight.owner,
+        airline_icao => designator(&flight.callsign),
+        airline_iata => flight.airline.as_ref().and_then(|a| a.iata.clone()),
+        airline_name => flight.airline.as_ref().map(|a| a.name.clone()),
+        airline_color => flight.airline.as_ref().map_or("#000000", |a| a.color.as_str()),
+        airline_logo => flight.airline_logo.clone().map(Value::from_safe_string),
+        distance_km => flight.distance,
+        bearing => flight.bearing,
+        compass => compass_point(flight.bearing),
+        range_km => MAX_DISTANCE_KM,
+        map_x => scale * bearing.sin(),
+        map_y => -scale * bearing.cos(),
+        altitude => flight.baro_altitude.or(flight.geo_altitude).map(|m| units.altitude(m)),
+        baro_altitude => flight.baro_altitude.map(|m| units.altitude(m)),
+        geo_altitude => flight.geo_altitude.map(|m| units.altitude(m)),
+        altitude_unit => units.altitude_unit(),
+        speed => flight.ground_speed.map(|v| units.speed(v)),
+        speed_unit => units.speed_unit(),
+        track => flight.track,
+        vertical_rate => flight.vertical_rate.map(|v| units.vertical_rate(v)),
+        vertical_rate_unit => units.vertical_rate_unit(),
+        squawk => flight.squawk,
+        telemetry => telemetry(flight, units),
+        // A data URI we built ourselves, so it doesn't need escaping.
+        photo => flight.photo_base64.clone().map(Value::from_safe_string),
+        photo_url => flight.photo_url,
+        origin_iata => flight.origin_iata,
+        origin_name => flight.origin_name,
+        dest_iata => flight.dest_iata,
+        dest_name => flight.dest_name,
+        origin_airport => flight.origin.as_ref().map(|a| a.name.clone()),
+        origin_country => flight.origin.as_ref().and_then(|a| a.country.clone()),
+        dest_airport => flight.destination.as_ref().map(|a| a.name.clone()),
+        dest_country => flight.destination.as_ref().and_then(|a| a.country.clone()),
+        route_km => progress.map(|p| p.route_km),
+        flown_km => progress.map(|p| p.flown_km),
+        remaining_km => progress.map(|p| p.remaining_km),
+        progress => progress.map(|p| p.fraction),
+        eta => flight.eta.map(|t| t.format("%H:%M").to_string()),
+        movement => flight.movement.map(Movement::name),
+        movement_label => flight.movement.map(Movement::label),
+        movement_color => flight.movement.map(|m| m.colors().0),
+        movement_text_color => flight.movement.map(|m| m.colors().1),
+        alert => flight.alert.map(Alert::name),
+        alert_label => flight.alert.map(Alert::label),
+    }
+}
+
+/// `country_badge(code, x, y)`, the ISO country code in an outlined box with its top left corner
+/// at (`x`, `y`), standing in for a flag, which the embedded font has no glyphs for. Empty for
+/// anything but two letters.
+fn country_badge(code: Option<&str>, x: f64, y: f64) -> String {
+    let Some(code) = code.filter(|c| c.len() == 2 && c.bytes().all(|b| b.is_ascii_alphabetic()))
+    else {
+        return String::new();
+    };
+    let mut svg = SvgWriter::new();
+    svg.element("rect")
+        .attr("x", x)
+        .attr("y", y)
+        .attr("width", COUNTRY_BADGE_WIDTH)
+        .attr("height", COUNTRY_BADGE_HEIGHT)
+        .attr("rx", 8)
+        .attr("fill", "#FFFFFF")
+        .attr("stroke", "#000000")
+        .attr("stroke-width", 3)
+        .empty();
+    svg.element("text")
+        .attr("x", x + COUNTRY_BADGE_WIDTH / 2.0)
+        .attr("y", y + 36.0)
+        .attr("font-family", "Google Sans, sans-serif")
+        .attr("font-size", 32)
+        .attr("text-anchor", "middle")
+        .attr("fill", "#000000")
+        .attr("font-weight", "bold")
+        .text(&code.to_ascii_uppercase());
+    svg.finish()
+}
+
+/// Renders `flight` with the layout called `name`. Errors name the layout and, for template
+/// errors, the line and the offending expression.
+pub fn render_layout(
+    dir: Option<&Path>,
+    name: &str,
+    flight: &Flight,
+    fitter: &TextFitter,
+    units: &UnitsConfig,
+    inset: &Inset,
+) -> Result<SvgLayers, String> {
+    let source = layout_source(dir, name)?;
+    let describe = |e: minijinja::Error| format!("layout '{}': {:#}", name, e);
+
+    let mut env = Environment::new();
+    env.set_formatter(|out, _state, value| {
+        if value.is_safe() {
+            write!(out, "{}", value)?;
+        } else {
+            out.write_str(&escape(&value.to_string()))?;
+        }
+        Ok(())
+    });
+    // Misspelt variables are errors instead of silently empty text.
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    // `fit(text, width, size, min_size=size / 2, bold=false)`, giving the `text` and `size` to use.
+    let fitter = fitter.clone();
+    env.add_function(
+        "fit",
+        move |text: String, width: f32, size: f32, kwargs: Kwargs| {
+            let min_size = kwargs.get::<Option<f32>>("min_size")?.unwrap_or(size / 2.0);
+            let bold = kwargs.get::<Option<bool>>("bold")?.unwrap_or(false);
+            kwargs.assert_all_used()?;
+            Ok(Value::from_serialize(
+                fitter.fit(&text, width, size, min_size, bold),
+            ))
+        },
+    );
+    // `map_inset(cx, cy, radius)`, the inset as SVG markup built with `SvgWriter`.
+    let map = inset.clone();
+    env.add_function("map_inset", move |cx: f64, cy: f64, radius: f64| {
+        Value::from_safe_string(map.render(cx, cy, radius))
+    });
+    // `country_badge(code, x, y)`, an 80 by 48 box with the country code.
+    env.add_function("country_badge", |code: Option<String>, x: f64, y: f64| {
+        Value::from_safe_string(country_badge(code.as_deref(), x, y))
+    });
+    env.add_template(name, &source).map_err(describe)?;
+
+    let template = env.get_template(name).map_err(describe)?;
+    let mut captured = template
+        .render_captured(context! {
+            show_map => inset.enabled,
+            ..flight_context(flight, units)
+        })
+        .map_err(describe)?;
+    captured.with_state_mut(|state| {
+        Ok(SvgLayers {
+            background: state.render_block("background").map_err(describe)?,
+            overlay: state.render_block("overlay").map_err(describe)?,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::airlines::Airline;
+    use crate::airports::Airport;
+    use crate::map::Plot;
+    use crate::text_fit::embedded as fitter;
+    use crate::units::{AltitudeUnit, SpeedUnit};
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+    use std::sync::Arc;
+
+    fn test_flight() -> Flight {
+        Flight {
+            icao24: "test".to_string(),
+            callsign: "TEST123".to_string(),
+            flight_number: Some("LX123".to_string()),
+            aircraft_type: Some("Airbus A320".to_string()),
+            registration: Some("HB-JLT".to_string()),
+            type_code: Some("A320".to_string()),
+            manufacturer: Some("Airbus".to_string()),
+            operator: Some("Swiss".to_string()),
+            owner: None,
+            latitude: 47.4,
+            longitude: 8.466,
+            distance: 5.0,
+            bearing: 90.0,
+            baro_altitude: Some(3749.04),
+            geo_altitude: Some(3800.0),
+            ground_speed: Some(216.0),
+            track: Some(270.0),
+            vertical_rate: Some(-5.0),
+            squawk: Some("1000".to_string()),
+            airline: None,
+            airline_logo: None,
+            photo_url: Some("http://example.com/photo.jpg".to_string()),
+            photo_base64: Some("data:image/jpeg;base64,VEVTVA==".to_string()),
+            origin_iata: Some("WAW".to_string()),
+            origin_name: Some("Warsaw".to_string()),
+            dest_iata: Some("ZRH".to_string()),
+            dest_name: Some("Zurich".to_string()),
+            origin: None,
+            destination: None,
+            eta: None,
+            movement: None,
+            alert: None,
+        }
+    }
+
+    fn render(name: &str, flight: &Flight) -> String {
+        render_layout(
+            None,
+            name,
+            flight,
+            &fitter(),
+            &UnitsConfig::default(),
+            &inset(),
+        )
+        .unwrap()
+        .to_svg()
+    }
+
+    fn inset() -> Inset {
+        Inset {
+            enabled: true,
+            observer: (47.4, 8.4),
+            range_km: 8.0,
+            aircraft: Some(Plot {
+                position: (47.4, 8.466),
+                track: Some(270.0),
+            }),
+            trail: Vec::new(),
+            others: vec![(47.45, 8.45)],
+            landmarks: Arc::new(Vec::new()),
+            basemap: Arc::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_render_photo_hero() {
+        let svg = render(DEFAULT_LAYOUT, &test_flight());
+        assert!(svg.contains("TEST123"));
+        assert!(svg.contains("LX123"));
+        assert!(svg.contains("WAW"));
+        assert!(svg.contains("ZRH"));
+        assert!(svg.contains("Airbus A320"));
+        assert!(svg.contains("data:image/jpeg;base64,VEVTVA=="));
+        assert!(svg.contains(">12,300 ft</text>"));
+        assert!(svg.contains(">-1,000 ft/min</text>"));
+        assert!(svg.contains(">5.0 km E</text>"));
+        assert!(svg.contains(">1000</text>"));
+        assert!(svg.contains("translate(1400.0, 380.0)"));
+
+        let metric = UnitsConfig {
+            altitude: AltitudeUnit::Meters,
+            speed: SpeedUnit::KilometersPerHour,
+        };
+        let mut flight = test_flight();
+        flight.squawk = None;
+        flight.track = None;
+        let hidden = Inset {
+            enabled: false,
+            ..inset()
+        };
+        let svg = render_layout(None, DEFAULT_LAYOUT, &flight, &fitter(), &metric, &hidden)
+            .unwrap()
+            .to_svg();
+        assert!(!svg.contains("inset-clip"));
+        assert!(svg.contains(">3,749 m</text>"));
+        assert!(svg.contains(">778 km/h</text>"));
+        assert!(!svg.contains("SQUAWK"));
+        assert!(!svg.contains("TRACK"));
+    }
+
+    #[test]
+    fn test_bundled_layouts_parse() {
+        let mut flight = test_flight();
+        flight.dest_name = Some("Saint-Denis & Réunion".to_string());
+        flight.origin_name =
+            Some("Llanfairpwllgwyngyllgogerychwyrndrobwllllantysiliogogogoch".repeat(2));
+        flight.flight_number = None;
+        flight.photo_base64 = None;
+
+        for (name, _) in BUNDLED {
+            let svg = render(name, &flight);
+            assert!(svg.contains("Saint-Denis &amp; Réunion"), "{}", name);
+            // Too long even at the smallest size.
+            assert!(svg.contains("Llanfairpwll"), "{}", name);
+            assert!(svg.contains("…</text>"), "{}", name);
+            usvg::Tree::from_str(&svg, &usvg::Options::default())
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+
+        let svg = render("map-centric", &flight);
+        // 5 of 8 km due east.
+        assert!(svg.contains("cx='840.0' cy='600.0'"), "{}", svg);
+        assert!(svg.contains(">HB-JLT · Swiss</text>"), "{}", svg);
+    }
+
+    #[test]
+    fn test_airline() {
+        let flight = Flight {
+            callsign: "SWR8VP".to_string(),
+            airline: Some(Airline {
+                icao: "SWR".to_string(),
+                iata: Some("LX".to_string()),
+                name: "Swiss".to_string(),
+                color: "#FF0000".to_string(),
+            }),
+            airline_logo: Some("data:image/svg+xml;base64,PHN2Zy8+".to_string()),
+            ..test_flight()
+        };
+        for (name, _) in BUNDLED {
+            let svg = render(name, &flight);
+            assert!(svg.to_lowercase().contains(">swiss"), "{}", name);
+            assert!(
+                svg.contains("href='data:image/svg+xml;base64,PHN2Zy8+'"),
+                "{}",
+                name
+            );
+        }
+
+        let svg = render(DEFAULT_LAYOUT, &flight);
+        assert!(svg.contains("height='12' fill='#FF0000'"));
+        assert!(!svg.contains(">→</text>"));
+        // Private flights keep the plain header.
+        let svg = render(DEFAULT_LAYOUT, &test_flight());
+        assert!(svg.contains(">FLIGHT</text>"));
+        assert!(svg.contains(">→</text>"));
+    }
+
+    #[test]
+    fn test_country_badge() {
+        let badge = country_badge(Some("ch"), 10.0, 20.0);
+        assert!(
+            badge.contains("<rect x='10' y='20' width='80' height='48'"),
+            "{}",
+            badge
+        );
+        assert!(badge.contains("<text x='50' y='56'"), "{}", badge);
+        assert!(badge.ends_with(">CH</text>"), "{}", badge);
+        for code in [None, Some(""), Some("CHE"), Some("<>")] {
+            assert_eq!(country_badge(code, 0.0, 0.0), "", "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let airport = |icao: &str, name: &str, country: &str, latitude, longitude| Airport {
+            icao: icao.to_string(),
+            iata: None,
+            name: name.to_string(),
+            city: None,
+            country: Some(country.to_string()),
+            latitude,
+            longitude,
+        };
+        let flight = Flight {
+            origin: Some(airport("EGLL", "Heathrow", "GB", 51.4706, -0.461941)),
+            destination: Some(airport("LSZH", "Zürich Airport", "CH", 47.458, 8.548)),
+            eta: Some(
+                chrono_tz::Europe::Zurich
+                    .with_ymd_and_hms(2026, 10, 18, 14, 35, 0)
+                    .unwrap(),
+            ),
+            ..test_flight()
+        };
+        for (name, _) in BUNDLED {
+            let svg = render(name, &flight);
+            if *name == "text-only" {
+                assert!(svg.contains(">Heathrow, GB</text>"), "{}", name);
+                assert!(svg.contains(">Zürich Airport, CH</text>"), "{}", name);
+            } else {
+                assert!(svg.contains(">Heathrow</text>"), "{}", name);
+                assert!(svg.contains("font-weight='bold'>GB</text>"), "{}", name);
+                assert!(svg.contains("font-weight='bold'>CH</text>"), "{}", name);
+            }
+            if *name != DEFAULT_LAYOUT {
+                assert!(
+                    svg.contains(">788 km · 99% flown · ETA 14:35</text>"),
+                    "{}",
+                    name
+                );
+            }
+        }
+
+        let svg = render(DEFAULT_LAYOUT, &flight);
+        assert!(svg.contains(">ETA 14:35</text>"));
+        assert!(
+            svg.contains("transform='translate(1054.2, -35)'"),
+            "{}",
+            svg
+        );
+        assert!(!svg.contains(">→</text>"));
+    }
+
+    #[test]
+    fn test_movement_badge() {
+        let flight = Flight {
+            movement: Some(Movement::Arrival),
+            ..test_flight()
+        };
+        for (name, _) in BUNDLED {
+            let svg = render(name, &flight);
+            assert!(svg.contains("rx='35' fill='#0000FF'"), "{}", name);
+            assert!(
+                svg.contains("fill='#FFFFFF' font-weight='bold'>ARRIVAL</text>"),
+                "{}",
+                name
+            );
+            assert!(
+                !render(name, &test_flight()).contains("ARRIVAL"),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_alert_banner() {
+        let flight = Flight {
+            alert: Some(Alert::Rare),
+            aircraft_type: Some("A380-800".to_string()),
+            ..test_flight()
+        };
+        for (name, _) in BUNDLED {
+            let svg = render(name, &flight);
+            assert!(
+                svg.contains("width='1600' height='80' fill='#FF0000'"),
+                "{}",
+                name
+            );
+            assert!(svg.contains(">RARE AIRCRAFT · A380-800</text>"), "{}", name);
+            let svg = render(
+                name,
+                &Flight {
+                    alert: Some(Alert::Emergency),
+                    ..test_flight()
+                },
+            );
+            assert!(svg.contains(">SQUAWK 7700 · EMERGENCY</text>"), "{}", name);
+            assert!(
+                !render(name, &test_flight()).contains("#FF0000' />"),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("custom.svg"),
+            "{% block background %}{% endblock %}{% block overlay %}<text>{{ callsign }}</text>{% endblock %}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("typo.svg"),
+            "{% block background %}{% endblock %}{% block overlay %}{{ calsign }}{% endblock %}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.svg"), "{% block overlay %}{{ callsign }").unwrap();
+
+        let layouts = Some(dir);
+        assert!(layout_names(layouts).contains(&"custom".to_string()));
+        let layers = render_layout(
+            layouts,
+            "custom",
+            &test_flight(),
+            &fitter(),
+            &UnitsConfig::default(),
+            &inset(),
+        )
+        .unwrap();
+        assert_eq!(layers.overlay, "<text>TEST123</text>");
+
+        let err = render_layout(
+            layouts,
+            "typo",
+            &test_flight(),
+            &fitter(),
+            &UnitsConfig::default(),
+            &inset(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.starts_with("layout 'typo':"), "{}", err);
+        assert!(err.contains("undefined"), "{}", err);
+        let err = render_layout(
+            layouts,
+            "broken",
+            &test_flight(),
+            &fitter(),
+            &UnitsConfig::default(),
+            &inset(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("(in broken:1)"), "{}", err);
+        let err = render_layout(
+            layouts,
+            "nope",
+            &test_flight(),
+            &fitter(),
+            &UnitsConfig::default(),
+            &inset(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("expected one of: broken, custom"), "{}", err);
+        assert!(check_layout(layouts, "../etc/passwd").is_err());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_any_metadata_parses(
+            callsign in any::<String>(),
+            flight_number in any::<Option<String>>(),
+            aircraft_type in any::<Option<String>>(),
+            origin_name in any::<Option<String>>(),
+            dest_iata in any::<Option<String>>(),
+        ) {
+            let flight = Flight {
+                callsign,
+                flight_number,
+                aircraft_type,
+                origin_name,
+                dest_iata,
+                ..test_flight()
+            };
+            for (name, _) in BUNDLED {
+                let svg = render(name, &flight);
+                prop_assert!(
+                    usvg::Tree::from_str(&svg, &usvg::Options::default()).is_ok(),
+                    "{}: {}", name, svg
+                );
+            }
+        }
+    }
+}
@@ -1,20 +1,69 @@
+mod aircraft_db;
+mod airlines;
+mod airports;
+mod alerts;
+mod basemap;
+mod compress;
+mod config;
+mod delta;
+mod geo;
+mod history;
+mod layouts;
+mod map;
+mod movement;
+mod notify;
+mod panel;
+mod refresh;
+mod runways;
+mod schedule;
+mod screens;
+mod svg;
+mod text_fit;
+mod tracks;
+mod traffic;
+mod units;
+
+use aircraft_db::{AircraftDb, AircraftRecord};
+use airlines::Airlines;
+use airports::{Airport, Airports, Progress};
+use alerts::{Alert, Alerts};
 use axum::{
-    Router,
+    Json, Router,
     body::Body,
-    extract::State,
+    extract::{Query, State},
+    http::{HeaderMap, HeaderValue, header},
+    middleware,
     response::{Html, IntoResponse, Response},
     routing::get,
 };
 use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, TimeDelta, Timelike, Utc};
+use chrono_tz::Tz;
+use compress::Compression;
+use config::Config;
+use history::History;
+use movement::{Movement, Observation};
+use notify::Notifier;
+use panel::{
+    LayoutOverrides, Mirror, PaletteEntry, PanelProfile, Rotation, StripLayout, pixmap_to_epd_bin,
+};
+use refresh::RefreshHint;
+use runways::RunwayWatch;
+use schedule::DisplayMode;
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use text_fit::TextFitter;
 use tiny_skia::Pixmap;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tower_http::trace::TraceLayer;
 use tracing::{error, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracks::Tracks;
+use traffic::Aircraft;
 use usvg::{Tree, fontdb};
 
 #[derive(Clone)]
@@ -25,11 +74,59 @@ struct OpenSkyToken {
 
 #[derive(Clone)]
 struct AppState {
+    config: Arc<Config>,
     usvg_options: Arc<usvg::Options<'static>>,
+    /// Measures text with the same fonts, to fit it to the layout.
+    text_fitter: TextFitter,
     client: reqwest::Client,
     opensky_client_id: Option<String>,
     opensky_client_secret: Option<String>,
     opensky_token: Arc<RwLock<Option<OpenSkyToken>>>,
+    traffic: Arc<RwLock<Option<TrafficSnapshot>>>,
+    /// Aircraft seen in range, for the summary screen.
+    history: Arc<RwLock<History>>,
+    /// The last live screen, for `hold` schedule windows and `/image.hash`.
+    last_layers: Arc<RwLock<Option<LastLayers>>>,
+    /// The last frame sent to each device, for partial refreshes.
+    device_frames: Arc<RwLock<HashMap<String, DeviceFrame>>>,
+    /// Recent positions of each aircraft, for trails on the radar inset.
+    tracks: Arc<RwLock<Tracks>>,
+    /// Landmarks drawn on the radar inset.
+    landmarks: Arc<Vec<map::Feature>>,
+    /// Water, roads and the like drawn under the radar inset.
+    basemap: Arc<Vec<map::Feature>>,
+    /// The airline table and logos.
+    airlines: Arc<Airlines>,
+    /// Local airport and route tables, empty unless configured.
+    airports: Arc<Airports>,
+    /// Rules for aircraft that stand out.
+    alerts: Arc<Alerts>,
+    /// Where aircraft with an alert are announced.
+    notifier: Arc<Notifier>,
+    /// Landings and departures seen on each runway.
+    runways: Arc<RwLock<RunwayWatch>>,
+    /// The local aircraft database, if one is configured.
+    aircraft_db: Arc<RwLock<Option<AircraftDb>>>,
+    /// Held while the aircraft database is reloaded.
+    aircraft_db_reload: Arc<Mutex<()>>,
+}
+
+struct TrafficSnapshot {
+    fetched_at: Instant,
+    aircraft: Vec<Aircraft>,
+}
+
+struct LastLayers {
+    layout: String,
+    rendered_at: Instant,
+    layers: SvgLayers,
+}
+
+struct DeviceFrame {
+    /// The panel profile the frame was rendered for.
+    profile: PanelProfile,
+    indices: Vec<u8>,
+    recorded_at: Instant,
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,20 +137,15 @@ struct TokenResponse {
 
 const FONT_DATA: &[u8] = include_bytes!("../GoogleSans-VariableFont_GRAD,opsz,wght.ttf");
 
-const PALETTE: [[u8; 3]; 6] = [
-    [0, 0, 0],       // Black
-    [255, 255, 255], // White
-    [255, 255, 0],   // Yellow
-    [255, 0, 0],     // Red
-    [0, 0, 255],     // Blue
-    [0, 255, 0],     // Green
-];
-
-const LAT: f64 = 47.41876326848794;
-const LON: f64 = 8.426291132310645;
 const BOX_SIZE: f64 = 0.15; // Increased to ensure we cover 8km radius
 const MAX_ALTITUDE_METERS: f64 = 6096.0; // 20,000 feet
 const MAX_DISTANCE_KM: f64 = 8.0;
+/// How many devices' last frames are kept for `/image.delta`.
+const MAX_DEVICE_FRAMES: usize = 16;
+/// How long `/refresh.json` reuses the traffic fetched for the last image.
+const TRAFFIC_MAX_AGE: Duration = Duration::from_secs(20);
+
+const NEXT_REFRESH_HEADER: &str = "X-Next-Refresh-Seconds";
 
 #[derive(Debug, Deserialize)]
 struct OpenSkyResponse {
@@ -75,6 +167,10 @@ struct AdsbdbData {
 struct AdsbdbAircraft {
     #[serde(rename = "type")]
     aircraft_type: String,
+    icao_type: Option<String>,
+    manufacturer: Option<String>,
+    registration: Option<String>,
+    registered_owner: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,8 +182,31 @@ struct AdsbdbFlightRoute {
 
 #[derive(Debug, Deserialize)]
 struct AdsbdbAirport {
+    icao_code: String,
     iata_code: String,
+    name: Option<String>,
     municipality: String,
+    country_iso_name: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+impl AdsbdbAirport {
+    /// The airport as in the airports table, if adsbdb knows where it is.
+    fn to_airport(&self) -> Option<Airport> {
+        Some(Airport {
+            icao: self.icao_code.clone(),
+            iata: Some(self.iata_code.clone()),
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| self.municipality.clone()),
+            city: Some(self.municipality.clone()),
+            country: self.country_iso_name.clone(),
+            latitude: self.latitude?,
+            longitude: self.longitude?,
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -111,23 +230,149 @@ struct Flight {
     callsign: String,
     flight_number: Option<String>,
     aircraft_type: Option<String>,
+    registration: Option<String>,
+    /// ICAO type designator, e.g. `A20N`.
+    type_code: Option<String>,
+    manufacturer: Option<String>,
+    operator: Option<String>,
+    owner: Option<String>,
+    latitude: f64,
+    longitude: f64,
     distance: f64,
+    bearing: f64,
+    /// Barometric altitude in meters.
+    baro_altitude: Option<f64>,
+    /// Geometric altitude in meters.
+    geo_altitude: Option<f64>,
+    /// Ground speed in m/s.
+    ground_speed: Option<f64>,
+    /// Track over ground in degrees clockwise from north.
+    track: Option<f64>,
+    /// Climb rate in m/s.
+    vertical_rate: Option<f64>,
+    squawk: Option<String>,
+    /// The airline, from the callsign prefix.
+    airline: Option<airlines::Airline>,
+    /// Data URI of the airline logo.
+    airline_logo: Option<String>,
     photo_url: Option<String>,
     photo_base64: Option<String>,
     origin_iata: Option<String>,
     origin_name: Option<String>,
     dest_iata: Option<String>,
     dest_name: Option<String>,
+    /// The origin, from the airports table or adsbdb.
+    origin: Option<Airport>,
+    /// The destination, from the airports table or adsbdb.
+    destination: Option<Airport>,
+    /// Estimated time of arrival at the destination.
+    eta: Option<DateTime<Tz>>,
+    /// Relative to the home airport, if one is configured.
+    movement: Option<Movement>,
+    /// Why the aircraft stands out, if it does.
+    alert: Option<Alert>,
+}
+
+impl Flight {
+    /// How far along the route the aircraft is, if both ends are known.
+    fn progress(&self) -> Option<Progress> {
+        let (origin, destination) = self.origin.as_ref().zip(self.destination.as_ref())?;
+        Some(Progress::new(
+            origin,
+            destination,
+            (self.latitude, self.longitude),
+        ))
+    }
+}
+
+/// A rendered screen, split into the photo layer and the vector overlay drawn on top of it so
+/// that the two can be rasterized differently.
+#[derive(Clone)]
+struct SvgLayers {
+    background: String,
+    overlay: String,
+}
+
+impl SvgLayers {
+    /// The complete SVG document with the overlay composited over the background.
+    fn to_svg(&self) -> String {
+        wrap_svg(&format!("{}\n{}", self.background, self.overlay))
+    }
+
+    fn background_svg(&self) -> String {
+        wrap_svg(&self.background)
+    }
+
+    /// The overlay on a transparent canvas, rendered without anti-aliasing.
+    fn overlay_svg(&self) -> String {
+        wrap_svg(&format!(
+            "<g shape-rendering='crispEdges' text-rendering='optimizeSpeed'>\n{}\n</g>",
+            self.overlay
+        ))
+    }
+}
+
+fn wrap_svg(content: &str) -> String {
+    format!(
+        "<svg width='1600' height='1200' viewBox='0 0 1600 1200' xmlns='http://www.w3.org/2000/svg'>\n{}\n</svg>",
+        content
+    )
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum DitherMode {
+    /// Dither the whole composited image.
+    #[default]
+    Full,
+    /// Dither only the photo layer and draw the text and vector overlay with crisp, exact palette
+    /// colours on top of it.
+    Layered,
+}
+
+impl DitherMode {
+    /// Name as in requests, e.g. `layered`.
+    fn name(self) -> &'static str {
+        match self {
+            DitherMode::Full => "full",
+            DitherMode::Layered => "layered",
+        }
+    }
 }
 
-fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let r = 6371.0; // Earth's radius in km
-    let d_lat = (lat2 - lat1).to_radians();
-    let d_lon = (lon2 - lon1).to_radians();
-    let a = (d_lat / 2.0).sin().powi(2)
-        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-    r * c
+#[derive(Debug, Default, Deserialize)]
+struct ImageParams {
+    #[serde(default)]
+    dither: DitherMode,
+    /// Panel profile name, overriding the configured one.
+    panel: Option<String>,
+    rotation: Option<Rotation>,
+    mirror: Option<Mirror>,
+    strips: Option<StripLayout>,
+    /// Wraps `/image.bin` in a frame with a header, optionally compressed.
+    compression: Option<Compression>,
+    /// Identifies the device, so that `/image.delta` knows what it shows and its configured
+    /// layout is used.
+    device: Option<String>,
+    /// Layout of the flight screen, overriding the device's and the configured one.
+    layout: Option<String>,
+}
+
+/// Reads the GeoJSON file at `path`, if one is configured, exiting if it can't be read.
+fn load_features(what: &str, path: Option<&std::path::Path>) -> Vec<map::Feature> {
+    let Some(path) = path else {
+        return Vec::new();
+    };
+    match map::load_geojson(path) {
+        Ok(features) => {
+            info!("Loaded {} {} features", features.len(), what);
+            features
+        }
+        Err(e) => {
+            error!("Error loading {}: {}", what, e);
+            std::process::exit(1);
+        }
+    }
 }
 
 #[tokio::main]
@@ -140,10 +385,40 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Error loading config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("preview") {
+        if let Err(e) = preview_command(&config, &args[1..]) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(profile) = panel::find_profile(&config.panel) {
+        info!(
+            "Using panel profile {}: {}",
+            profile.name, profile.description
+        );
+    }
+
+    let landmarks = load_features("landmarks", config.map.landmarks.as_deref());
+    let basemap = load_features("basemap", config.map.basemap.as_deref());
+
     let mut fontdb = fontdb::Database::new();
     fontdb.load_font_data(FONT_DATA.to_vec());
-    let mut usvg_options = usvg::Options::default();
-    usvg_options.fontdb = Arc::new(fontdb);
+    let fontdb = Arc::new(fontdb);
+    let text_fitter = TextFitter::new(fontdb.clone());
+    let usvg_options = usvg::Options {
+        fontdb,
+        ..Default::default()
+    };
 
     let client = reqwest::Client::builder()
         .user_agent("Radar/0.1.0")
@@ -159,20 +434,80 @@ async fn main() {
         info!("OpenSky OAuth2 credentials not found, using anonymous requests.");
     }
 
+    let airlines = Airlines::new(&config.airlines);
+    let alerts = Alerts::new(&config.alerts).expect("alerts are checked with the config");
+    let airports = match Airports::load(&config.airports) {
+        Ok(airports) => {
+            if config.airports.airports.is_some() {
+                info!(
+                    "Loaded {} airports and {} routes",
+                    airports.len(),
+                    airports.route_count()
+                );
+            }
+            airports
+        }
+        Err(e) => {
+            error!("Error loading airports: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let aircraft_db = config
+        .aircraft_db
+        .path
+        .as_deref()
+        .map(|path| match AircraftDb::load(path) {
+            Ok(db) => {
+                info!("Loaded {} aircraft from {}", db.len(), path.display());
+                db
+            }
+            Err(e) => {
+                error!("Error loading aircraft database: {}", e);
+                std::process::exit(1);
+            }
+        });
+    let runways = RunwayWatch::new(config.runways.clone());
+    let notifier = Notifier::new(&config.notifications, client.clone());
+    let tracks = Tracks::new(TimeDelta::minutes(config.map.trail_minutes.into()));
     let state = AppState {
+        config: Arc::new(config),
         usvg_options: Arc::new(usvg_options),
+        text_fitter,
         client,
         opensky_client_id,
         opensky_client_secret,
         opensky_token: Arc::new(RwLock::new(None)),
+        traffic: Arc::new(RwLock::new(None)),
+        history: Arc::new(RwLock::new(History::default())),
+        last_layers: Arc::new(RwLock::new(None)),
+        device_frames: Arc::new(RwLock::new(HashMap::new())),
+        tracks: Arc::new(RwLock::new(tracks)),
+        landmarks: Arc::new(landmarks),
+        basemap: Arc::new(basemap),
+        airlines: Arc::new(airlines),
+        alerts: Arc::new(alerts),
+        notifier: Arc::new(notifier),
+        airports: Arc::new(airports),
+        runways: Arc::new(RwLock::new(runways)),
+        aircraft_db: Arc::new(RwLock::new(aircraft_db)),
+        aircraft_db_reload: Arc::new(Mutex::new(())),
     };
 
     let app = Router::new()
-        .route("/", get(index))
         .route("/image.svg", get(get_image))
         .route("/image.png", get(get_image_png))
         .route("/image_dithered.png", get(get_image_dithered_png))
         .route("/image.bin", get(get_image_bin))
+        .route("/image.hash", get(get_image_hash))
+        .route("/image.delta", get(get_image_delta))
+        .route("/preview.png", get(get_preview_png))
+        .route("/refresh.json", get(get_refresh))
+        .route("/runways.json", get(get_runways))
+        .route_layer(middleware::map_response_with_state(
+            state.clone(),
+            add_refresh_header,
+        ))
+        .route("/", get(index))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
@@ -185,106 +520,840 @@ async fn main() {
 
 async fn index() -> Html<&'static str> {
     Html(
-        "<h1>Radar</h1><ul><li><a href='/image.svg'>/image.svg</a></li><li><a href='/image.png'>/image.png</a></li><li><a href='/image_dithered.png'>/image_dithered.png</a></li><li><a href='/image.bin'>/image.bin</a></li></ul>",
+        "<h1>Radar</h1><ul><li><a href='/image.svg'>/image.svg</a></li><li><a href='/image.png'>/image.png</a></li><li><a href='/image_dithered.png'>/image_dithered.png</a></li><li><a href='/image_dithered.png?dither=layered'>/image_dithered.png?dither=layered</a></li><li><a href='/image.bin'>/image.bin</a></li><li><a href='/image.hash'>/image.hash</a></li><li><a href='/preview.png'>/preview.png</a></li><li><a href='/refresh.json'>/refresh.json</a></li><li><a href='/runways.json'>/runways.json</a></li></ul>",
     )
 }
 
-async fn get_image(State(state): State<AppState>) -> impl IntoResponse {
-    match fetch_svg(&state).await {
-        Ok(svg) => make_response("image/svg+xml", svg),
-        Err(resp) => resp,
+async fn get_image(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
+        Err(resp) => return resp,
+    };
+
+    let svg = layers.to_svg();
+    let etag = content_etag(&["svg", &svg]);
+    if is_not_modified(&headers, &etag) {
+        return not_modified(&etag);
     }
+    make_response("image/svg+xml", &etag, svg)
 }
 
-async fn get_image_png(State(state): State<AppState>) -> impl IntoResponse {
-    let svg = match fetch_svg(&state).await {
-        Ok(svg) => svg,
+async fn get_image_png(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
         Err(resp) => return resp,
     };
 
+    let svg = layers.to_svg();
+    let etag = content_etag(&["png", &svg]);
+    if is_not_modified(&headers, &etag) {
+        return not_modified(&etag);
+    }
     handle_result(
         svg_to_png(&svg, &state.usvg_options),
         "image/png",
+        &etag,
         "Error rendering PNG",
     )
 }
 
-async fn get_image_dithered_png(State(state): State<AppState>) -> impl IntoResponse {
-    let svg = match fetch_svg(&state).await {
-        Ok(svg) => svg,
+async fn get_image_dithered_png(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let profile = match resolve_panel(&state.config, &params) {
+        Ok(profile) => profile,
+        Err(msg) => return bad_request(msg),
+    };
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
         Err(resp) => return resp,
     };
 
+    let etag = content_etag(&[
+        "dithered",
+        &layers.to_svg(),
+        &output_key(&profile, params.dither, None),
+    ]);
+    if is_not_modified(&headers, &etag) {
+        return not_modified(&etag);
+    }
     handle_result(
-        svg_to_dithered_png(&svg, &state.usvg_options),
+        layers_to_dithered_png(&layers, params.dither, &profile, &state.usvg_options),
         "image/png",
+        &etag,
         "Error rendering dithered PNG",
     )
 }
 
-async fn get_image_bin(State(state): State<AppState>) -> impl IntoResponse {
-    let svg = match fetch_svg(&state).await {
-        Ok(svg) => svg,
+async fn get_image_bin(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let profile = match resolve_panel(&state.config, &params) {
+        Ok(profile) => profile,
+        Err(msg) => return bad_request(msg),
+    };
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
         Err(resp) => return resp,
     };
 
-    handle_result(
-        svg_to_epd_bin(&svg, &state.usvg_options),
-        "application/octet-stream",
-        "Error rendering BIN",
-    )
+    let (compression, negotiated) = bin_compression(&params, &headers);
+    let etag = bin_etag(&layers, &profile, &params, compression);
+    if is_not_modified(&headers, &etag) {
+        return not_modified(&etag);
+    }
+
+    let indices =
+        match layers_to_native_indices(&layers, params.dither, &profile, &state.usvg_options) {
+            Ok(indices) => indices,
+            Err(e) => return server_error("Error rendering BIN", e),
+        };
+    let bin = panel::indices_to_epd_bin(&indices, &profile);
+    if let Some(device) = &params.device {
+        record_device_frame(&state, device, &profile, indices).await;
+    }
+
+    let body = match compression {
+        Some(compression) => compress::encode_frame(&bin, compression, profile.id),
+        None => bin,
+    };
+    let mut resp = make_response("application/octet-stream", &etag, body);
+    if negotiated {
+        resp.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(compress::RLE_CONTENT_ENCODING),
+        );
+    }
+    resp.headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    resp
 }
 
-fn make_response(content_type: &str, body: impl Into<Body>) -> Response {
+/// The rectangles that changed since the last frame sent to `?device=`, for panels that support
+/// partial refresh. Devices without a previous frame get the whole panel as one rectangle.
+async fn get_image_delta(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+) -> impl IntoResponse {
+    let Some(device) = params.device.clone() else {
+        return bad_request("Missing ?device=".to_string());
+    };
+    let profile = match resolve_panel(&state.config, &params) {
+        Ok(profile) => profile,
+        Err(msg) => return bad_request(msg),
+    };
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
+        Err(resp) => return resp,
+    };
+
+    let indices =
+        match layers_to_native_indices(&layers, params.dither, &profile, &state.usvg_options) {
+            Ok(indices) => indices,
+            Err(e) => return server_error("Error rendering delta", e),
+        };
+
+    let delta = {
+        let frames = state.device_frames.read().await;
+        let previous = frames
+            .get(&device)
+            .filter(|f| f.profile == profile)
+            .map(|f| f.indices.as_slice());
+        delta::encode_delta(previous, &indices, &profile)
+    };
+    record_device_frame(&state, &device, &profile, indices).await;
+
+    Response::builder()
+        .header("Content-Type", "application/octet-stream")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(delta))
+        .unwrap()
+}
+
+async fn record_device_frame(
+    state: &AppState,
+    device: &str,
+    profile: &PanelProfile,
+    indices: Vec<u8>,
+) {
+    let frame = DeviceFrame {
+        profile: profile.clone(),
+        indices,
+        recorded_at: Instant::now(),
+    };
+    let mut frames = state.device_frames.write().await;
+    // Device ids come from the query, so make room by forgetting the device heard from longest
+    // ago rather than keeping a frame for every id ever sent.
+    if !frames.contains_key(device)
+        && frames.len() >= MAX_DEVICE_FRAMES
+        && let Some(oldest) = (frames.iter())
+            .min_by_key(|(_, frame)| frame.recorded_at)
+            .map(|(device, _)| device.clone())
+    {
+        frames.remove(&oldest);
+    }
+    frames.insert(device.to_string(), frame);
+}
+
+/// The `ETag` `/image.bin` would return for the same request, without rendering it. The device
+/// can poll this and only fetch the framebuffer and refresh the panel when it changes.
+async fn get_image_hash(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let profile = match resolve_panel(&state.config, &params) {
+        Ok(profile) => profile,
+        Err(msg) => return bad_request(msg),
+    };
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match recent_layers(&state, &layout).await {
+        Ok(layers) => layers,
+        Err(resp) => return resp,
+    };
+
+    let (compression, _) = bin_compression(&params, &headers);
+    let etag = bin_etag(&layers, &profile, &params, compression);
+    make_response("text/plain", &etag, etag.clone())
+}
+
+/// The framing requested for `/image.bin`, and whether it was picked through `Accept-Encoding`.
+/// An explicit `?compression=` wins over content negotiation.
+fn bin_compression(params: &ImageParams, headers: &HeaderMap) -> (Option<Compression>, bool) {
+    let negotiated = params.compression.is_none() && accepts_rle(headers);
+    let compression = params
+        .compression
+        .or(negotiated.then_some(Compression::Rle));
+    (compression, negotiated)
+}
+
+fn bin_etag(
+    layers: &SvgLayers,
+    profile: &PanelProfile,
+    params: &ImageParams,
+    compression: Option<Compression>,
+) -> String {
+    content_etag(&[
+        "bin",
+        &layers.to_svg(),
+        &output_key(profile, params.dither, compression),
+    ])
+}
+
+fn accepts_rle(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            // A q-value that doesn't parse counts as refused, like `q=0`.
+            let refused = parts.any(|p| {
+                p.split_once('=').is_some_and(|(key, q)| {
+                    key.trim().eq_ignore_ascii_case("q")
+                        && !(q.trim().parse::<f32>().is_ok_and(|q| q > 0.0))
+                })
+            });
+            name.eq_ignore_ascii_case(compress::RLE_CONTENT_ENCODING) && !refused
+        })
+}
+
+async fn get_preview_png(
+    State(state): State<AppState>,
+    Query(params): Query<ImageParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let profile = match resolve_panel(&state.config, &params) {
+        Ok(profile) => profile,
+        Err(msg) => return bad_request(msg),
+    };
+    let layout = match resolve_layout(&state.config, &params) {
+        Ok(layout) => layout,
+        Err(msg) => return bad_request(msg),
+    };
+    let layers = match fetch_layers(&state, &layout).await {
+        Ok(layers) => layers,
+        Err(resp) => return resp,
+    };
+
+    let etag = content_etag(&[
+        "preview",
+        &layers.to_svg(),
+        &output_key(&profile, params.dither, None),
+    ]);
+    if is_not_modified(&headers, &etag) {
+        return not_modified(&etag);
+    }
+    let result = layers_to_epd_bin(&layers, params.dither, &profile, &state.usvg_options)
+        .and_then(|bin| epd_bin_to_png(&bin, &profile));
+    handle_result(result, "image/png", &etag, "Error rendering preview")
+}
+
+/// `radar preview <input.bin> <output.png> [key=value...]` renders a framebuffer captured from
+/// `/image.bin`, raw or framed, as it will look on the panel. The options are the `/image.bin` query parameters
+/// that affect the layout, e.g. `panel=mono-7in5 rotation=180`.
+fn preview_command(config: &Config, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [input, output, options @ ..] = args else {
+        return Err(
+            "usage: radar preview <input.bin> <output.png> [panel=NAME] [rotation=DEG] [mirror=MODE] [strips=LAYOUT]"
+                .into(),
+        );
+    };
+
+    let uri: axum::http::Uri = format!("/preview.png?{}", options.join("&")).parse()?;
+    let Query(params) = Query::<ImageParams>::try_from_uri(&uri)?;
+    let profile = resolve_panel(config, &params)?;
+
+    let mut bin = std::fs::read(input)?;
+    if compress::is_frame(&bin) {
+        let frame = compress::decode_frame(&bin)?;
+        if frame.panel_id != profile.id {
+            let framed = panel::find_profile_by_id(frame.panel_id).map_or("unknown", |p| p.name);
+            return Err(format!(
+                "frame was packed for panel {} ({}), not {}",
+                frame.panel_id, framed, profile.name
+            )
+            .into());
+        }
+        bin = frame.data;
+    }
+    std::fs::write(output, epd_bin_to_png(&bin, &profile)?)?;
+    info!("Wrote {} preview to {}", profile.name, output);
+    Ok(())
+}
+
+fn epd_bin_to_png(
+    bin: &[u8],
+    profile: &PanelProfile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Ok(panel::epd_bin_to_pixmap(bin, profile)?.encode_png()?)
+}
+
+/// The panel profile for a request: the requested or configured profile, with the configured
+/// layout overrides and then the requested ones applied.
+fn resolve_panel(config: &Config, params: &ImageParams) -> Result<PanelProfile, String> {
+    let name = params.panel.as_deref().unwrap_or(&config.panel);
+    let profile = panel::find_profile(name).ok_or_else(|| {
+        format!(
+            "Unknown panel '{}', expected one of: {}",
+            name,
+            panel::profile_names().join(", ")
+        )
+    })?;
+    Ok(profile
+        .with_overrides(&config.panel_layout)
+        .with_overrides(&LayoutOverrides {
+            rotation: params.rotation,
+            mirror: params.mirror,
+            strips: params.strips,
+        }))
+}
+
+/// The flight screen layout for the request: `?layout=`, else the device's, else the configured one.
+fn resolve_layout(config: &Config, params: &ImageParams) -> Result<String, String> {
+    let device_layout = params
+        .device
+        .as_ref()
+        .and_then(|device| config.devices.get(device))
+        .and_then(|device| device.layout.as_ref());
+    let layout = params
+        .layout
+        .as_ref()
+        .or(device_layout)
+        .unwrap_or(&config.layout);
+    layouts::check_layout(config.layouts_dir.as_deref(), layout)?;
+    Ok(layout.clone())
+}
+
+fn bad_request(msg: String) -> Response {
+    Response::builder()
+        .status(400)
+        .body(Body::from(msg))
+        .unwrap()
+}
+
+fn make_response(content_type: &str, etag: &str, body: impl Into<Body>) -> Response {
     Response::builder()
         .header("Content-Type", content_type)
-        .header("Cache-Control", "no-cache, no-store, must-revalidate")
+        .header("Cache-Control", "no-cache")
+        .header("ETag", etag)
         .body(body.into())
         .unwrap()
 }
 
-fn handle_result<T, E>(result: Result<T, E>, content_type: &str, error_msg: &str) -> Response
+fn not_modified(etag: &str) -> Response {
+    Response::builder()
+        .status(304)
+        .header("Cache-Control", "no-cache")
+        .header("ETag", etag)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// The settings an image depends on besides its layers, for `content_etag`. Spelled out rather
+/// than taken from `Debug`, which changes whenever the structs do.
+fn output_key(
+    profile: &PanelProfile,
+    dither: DitherMode,
+    compression: Option<Compression>,
+) -> String {
+    format!(
+        "{} {}x{} {}bpp rotation={} mirror={} strips={} dither={} compression={}",
+        profile.name,
+        profile.width,
+        profile.height,
+        profile.bits_per_pixel,
+        profile.rotation.degrees(),
+        profile.mirror.name(),
+        profile.strips.name(),
+        dither.name(),
+        compression.map_or("-", Compression::name),
+    )
+}
+
+/// A strong `ETag` over everything that determines a response body. Computed before rendering so
+/// that unchanged images can be answered with 304 without rasterizing them.
+///
+/// A CRC-32 of the length-delimited parts, which unlike `DefaultHasher` stays the same across
+/// builds, so that devices keep their cached frames when the server is updated.
+fn content_etag(parts: &[&str]) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    for part in parts {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("\"{:08x}\"", hasher.finalize())
+}
+
+/// Whether `If-None-Match` lists `etag`, ignoring weak validator prefixes as RFC 9110 requires for
+/// this header.
+fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn handle_result<T, E>(
+    result: Result<T, E>,
+    content_type: &str,
+    etag: &str,
+    error_msg: &str,
+) -> Response
 where
     T: Into<Body>,
     E: std::fmt::Display,
 {
     match result {
-        Ok(data) => make_response(content_type, data),
-        Err(e) => {
-            error!("{}: {}", error_msg, e);
-            Response::builder()
-                .status(500)
-                .body(Body::from(format!("{}: {}", error_msg, e)))
-                .unwrap()
-        }
+        Ok(data) => make_response(content_type, etag, data),
+        Err(e) => server_error(error_msg, e),
     }
 }
 
-async fn fetch_svg(state: &AppState) -> Result<String, Response> {
+fn server_error(error_msg: &str, e: impl std::fmt::Display) -> Response {
+    error!("{}: {}", error_msg, e);
+    Response::builder()
+        .status(500)
+        .body(Body::from(format!("{}: {}", error_msg, e)))
+        .unwrap()
+}
+
+async fn fetch_layers(state: &AppState, layout: &str) -> Result<SvgLayers, Response> {
+    let now = local_now(&state.config);
+    if let Some(window) = schedule::active_window(&state.config.schedule, now.time()) {
+        return Ok(scheduled_layers(state, window.mode, now).await);
+    }
+
     let start = std::time::Instant::now();
-    let token = get_opensky_token(state).await;
-    let fetch_result = fetch_closest_flight(&state.client, token.as_deref()).await;
+    let traffic = update_traffic(state).await;
+    let (selected, alerted) = match &traffic {
+        Ok(traffic) => {
+            // Types come from the local aircraft database only, as asking adsbdb about every
+            // aircraft around would take too long.
+            let db = state.aircraft_db.read().await;
+            let alert = |aircraft: &Aircraft| {
+                let record = db.as_ref().and_then(|db| db.get(&aircraft.icao24));
+                let type_code = record.and_then(|r| r.type_code.as_deref());
+                state.alerts.check(aircraft, type_code)
+            };
+            let alerted: Vec<Aircraft> = traffic
+                .iter()
+                .filter(|a| in_range(a) && alert(a).is_some())
+                .cloned()
+                .collect();
+            (
+                select_flight(traffic, alert, state.alerts.priority_km),
+                alerted,
+            )
+        }
+        Err(_) => (None, Vec::new()),
+    };
+    let mut flight = match selected {
+        Some(aircraft) => Some(fetch_flight_details(state, aircraft).await),
+        None => None,
+    };
+    // Before the photo is downloaded, which events don't carry.
+    announce_alerts(state, alerted, flight.as_ref());
+    if let Some(flight) = &mut flight {
+        download_photo(&state.client, flight).await;
+    }
     let fetch_duration = start.elapsed();
 
-    match fetch_result {
-        Ok(Some(flight)) => {
-            info!("Flight found: fetch={:?}", fetch_duration);
-            Ok(render_svg(&flight))
+    let layers = match (traffic, flight) {
+        (Ok(traffic), Some(flight)) => {
+            info!(
+                "Flight found: {} at {:.1} km, fetch={:?}",
+                flight.callsign, flight.distance, fetch_duration
+            );
+            if let Some(alert) = flight.alert {
+                info!("Alert for {}: {}", flight.callsign, alert.name());
+            }
+            match layouts::render_layout(
+                state.config.layouts_dir.as_deref(),
+                layout,
+                &flight,
+                &state.text_fitter,
+                &state.config.units,
+                &map_inset(state, &traffic, &flight).await,
+            ) {
+                Ok(layers) => layers,
+                Err(e) => {
+                    error!("Error rendering flight: {}", e);
+                    return Err(server_error("Error rendering flight", e));
+                }
+            }
         }
-        Ok(None) => {
+        (Ok(traffic), None) => {
             info!("No flight found: fetch={:?}", fetch_duration);
-            Ok(render_no_flight_svg())
+            idle_layers(state, &traffic, now).await
         }
-        Err(e) => {
+        (Err(e), _) => {
             error!("Error fetching flight: {} (took {:?})", e, fetch_duration);
-            Err(Response::builder()
+            return Err(Response::builder()
                 .status(500)
                 .body(Body::from(format!("Error: {}", e)))
-                .unwrap())
+                .unwrap());
         }
+    };
+    *state.last_layers.write().await = Some(LastLayers {
+        layout: layout.to_string(),
+        rendered_at: Instant::now(),
+        layers: layers.clone(),
+    });
+    Ok(layers)
+}
+
+/// Announces the aircraft in range with an alert that haven't been announced today, in the
+/// background. The one on screen is announced with the details already fetched for it, which
+/// may have found an alert the others' quick check can't, and the others' details are fetched
+/// first.
+fn announce_alerts(state: &AppState, alerted: Vec<Aircraft>, shown: Option<&Flight>) {
+    if !state.notifier.is_enabled() {
+        return;
+    }
+    let today = local_now(&state.config).date_naive();
+    let shown = shown.filter(|flight| flight.alert.is_some());
+    if let Some(flight) = shown
+        && state.notifier.first_today(&flight.icao24, today)
+    {
+        let state = state.clone();
+        let flight = flight.clone();
+        tokio::spawn(async move { announce(&state, &flight).await });
+    }
+    for aircraft in alerted {
+        if shown.is_some_and(|flight| flight.icao24 == aircraft.icao24)
+            || !state.notifier.first_today(&aircraft.icao24, today)
+        {
+            continue;
+        }
+        let state = state.clone();
+        tokio::spawn(async move {
+            let flight = fetch_flight_details(&state, &aircraft).await;
+            if flight.alert.is_some() {
+                announce(&state, &flight).await;
+            }
+        });
+    }
+}
+
+async fn announce(state: &AppState, flight: &Flight) {
+    info!(
+        "Announcing {} ({})",
+        flight.callsign,
+        flight.alert.map_or("", Alert::name)
+    );
+    let event = flight_event(flight, &state.notifier.image_url(), Utc::now());
+    state.notifier.send(&event).await;
+}
+
+/// The event announcing `flight`: its details, without the photo data.
+fn flight_event(flight: &Flight, image_url: &str, time: DateTime<Utc>) -> serde_json::Value {
+    let airport = |airport: &Option<Airport>| {
+        airport.as_ref().map(|a| {
+            json!({
+                "icao": a.icao,
+                "iata": a.iata,
+                "name": a.name,
+                "city": a.city,
+                "country": a.country,
+            })
+        })
+    };
+    json!({
+        "time": time.to_rfc3339(),
+        "image_url": image_url,
+        "alert": flight.alert.map(Alert::name),
+        "alert_label": flight.alert.map(Alert::label),
+        "icao24": flight.icao24,
+        "callsign": flight.callsign.trim(),
+        "flight_number": flight.flight_number,
+        "airline": flight.airline.as_ref().map(|a| &a.name),
+        "aircraft_type": flight.aircraft_type,
+        "type_code": flight.type_code,
+        "registration": flight.registration,
+        "manufacturer": flight.manufacturer,
+        "operator": flight.operator,
+        "owner": flight.owner,
+        "latitude": flight.latitude,
+        "longitude": flight.longitude,
+        "distance_km": flight.distance,
+        "bearing": flight.bearing,
+        "baro_altitude": flight.baro_altitude,
+        "geo_altitude": flight.geo_altitude,
+        "ground_speed": flight.ground_speed,
+        "track": flight.track,
+        "vertical_rate": flight.vertical_rate,
+        "squawk": flight.squawk,
+        "origin_iata": flight.origin_iata,
+        "origin_name": flight.origin_name,
+        "dest_iata": flight.dest_iata,
+        "dest_name": flight.dest_name,
+        "origin": airport(&flight.origin),
+        "destination": airport(&flight.destination),
+        "eta": flight.eta.map(|t| t.to_rfc3339()),
+        "movement": flight.movement.map(Movement::name),
+        "photo_url": flight.photo_url,
+    })
+}
+
+/// Like `fetch_layers`, but reuses the last screen rendered with `layout` while it is less than
+/// `TRAFFIC_MAX_AGE` old, so that polling doesn't fetch traffic and flight details every time.
+async fn recent_layers(state: &AppState, layout: &str) -> Result<SvgLayers, Response> {
+    let now = local_now(&state.config);
+    if schedule::active_window(&state.config.schedule, now.time()).is_none()
+        && let Some(last) = state.last_layers.read().await.as_ref()
+        && last.layout == layout
+        && last.rendered_at.elapsed() < TRAFFIC_MAX_AGE
+    {
+        return Ok(last.layers.clone());
+    }
+    fetch_layers(state, layout).await
+}
+
+/// The radar inset around `flight` and its trail, with the rest of `traffic` as dots.
+async fn map_inset(state: &AppState, traffic: &[Aircraft], flight: &Flight) -> map::Inset {
+    map::Inset {
+        enabled: state.config.map.inset,
+        observer: state.config.observer.position(),
+        range_km: state.config.map.range_km,
+        aircraft: Some(map::Plot {
+            position: (flight.latitude, flight.longitude),
+            track: flight.track,
+        }),
+        trail: state.tracks.read().await.trail(&flight.icao24),
+        others: traffic
+            .iter()
+            .filter(|a| a.icao24 != flight.icao24 && !a.on_ground)
+            .map(|a| (a.latitude, a.longitude))
+            .collect(),
+        landmarks: state.landmarks.clone(),
+        basemap: state.basemap.clone(),
+    }
+}
+
+/// The screen for a schedule window. Doesn't fetch any traffic.
+async fn scheduled_layers(state: &AppState, mode: DisplayMode, now: DateTime<Tz>) -> SvgLayers {
+    match mode {
+        DisplayMode::Night => {
+            screens::render_night_svg(schedule::live_again(&state.config.schedule, now))
+        }
+        DisplayMode::Summary => screens::render_summary_svg(&*state.history.read().await, now),
+        DisplayMode::Hold => {
+            let last = (state.last_layers.read().await.as_ref()).map(|last| last.layers.clone());
+            match last {
+                Some(layers) => layers,
+                None => idle_layers(state, &[], now).await,
+            }
+        }
+    }
+}
+
+async fn idle_layers(state: &AppState, traffic: &[Aircraft], now: DateTime<Tz>) -> SvgLayers {
+    let nearest = traffic
+        .iter()
+        .filter(|a| state.config.idle.show_nearest && !a.on_ground && !in_range(a))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance));
+    let runways = if state.config.idle.show_runways {
+        state.runways.read().await.in_use().summary()
+    } else {
+        None
+    };
+    screens::render_idle_svg(
+        now,
+        &*state.history.read().await,
+        nearest,
+        runways.as_deref(),
+        &state.text_fitter,
+    )
+}
+
+fn local_now(config: &Config) -> DateTime<Tz> {
+    Utc::now().with_timezone(&config.timezone)
+}
+
+/// Fetches the aircraft around the observer and keeps them for refresh hints.
+async fn update_traffic(state: &AppState) -> Result<Vec<Aircraft>, String> {
+    let token = get_opensky_token(state).await;
+    let aircraft = fetch_traffic(
+        &state.client,
+        token.as_deref(),
+        state.config.observer.position(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    let now = Utc::now();
+    state
+        .history
+        .write()
+        .await
+        .record(aircraft.iter().filter(|a| in_range(a)), now);
+    state.tracks.write().await.record(&aircraft, now);
+    state.runways.write().await.observe(&aircraft, now);
+    *state.traffic.write().await = Some(TrafficSnapshot {
+        fetched_at: Instant::now(),
+        aircraft: aircraft.clone(),
+    });
+    Ok(aircraft)
+}
+
+/// The latest traffic, fetched again if it is older than `TRAFFIC_MAX_AGE`.
+async fn recent_traffic(state: &AppState) -> Result<Vec<Aircraft>, String> {
+    if let Some(snapshot) = state.traffic.read().await.as_ref()
+        && snapshot.fetched_at.elapsed() < TRAFFIC_MAX_AGE
+    {
+        return Ok(snapshot.aircraft.clone());
     }
+    update_traffic(state).await
 }
 
+fn refresh_hint(config: &Config, traffic: &[Aircraft]) -> RefreshHint {
+    let now = local_now(config);
+    if let Some(hint) = scheduled_hint(config, now) {
+        return hint;
+    }
+
+    let overhead = traffic.iter().any(in_range);
+    let next_arrival = traffic
+        .iter()
+        .filter(|a| a.baro_altitude.is_none_or(|alt| alt <= MAX_ALTITUDE_METERS))
+        .filter_map(|a| a.seconds_until_within(config.observer.position(), MAX_DISTANCE_KM))
+        .min_by(f64::total_cmp);
+    refresh::next_refresh(overhead, next_arrival, now.hour(), &config.refresh)
+}
+
+/// During a schedule window the device can sleep until live traffic resumes.
+fn scheduled_hint(config: &Config, now: DateTime<Tz>) -> Option<RefreshHint> {
+    let window = schedule::active_window(&config.schedule, now.time())?;
+    let seconds = match schedule::live_again(&config.schedule, now) {
+        Some(resume) => {
+            ((resume - now).num_seconds().max(0) as u64).max(config.refresh.min_seconds)
+        }
+        None => config.refresh.night_seconds,
+    };
+    Some(RefreshHint {
+        seconds,
+        reason: window.mode.reason(),
+    })
+}
+
+/// Tells the device how long to sleep, based on the traffic the response was rendered from.
+async fn add_refresh_header(State(state): State<AppState>, mut resp: Response) -> Response {
+    let hint = match state.traffic.read().await.as_ref() {
+        Some(snapshot) => Some(refresh_hint(&state.config, &snapshot.aircraft)),
+        None => scheduled_hint(&state.config, local_now(&state.config)),
+    };
+    if let Some(hint) = hint {
+        resp.headers_mut()
+            .insert(NEXT_REFRESH_HEADER, HeaderValue::from(hint.seconds));
+    }
+    resp
+}
+
+async fn get_refresh(State(state): State<AppState>) -> impl IntoResponse {
+    let (hint, aircraft_in_range) = match scheduled_hint(&state.config, local_now(&state.config)) {
+        Some(hint) => (hint, None),
+        None => match recent_traffic(&state).await {
+            Ok(traffic) => (
+                refresh_hint(&state.config, &traffic),
+                Some(traffic.iter().filter(|a| in_range(a)).count()),
+            ),
+            Err(e) => return server_error("Error fetching flight", e),
+        },
+    };
+
+    Json(json!({
+        "next_refresh_seconds": hint.seconds,
+        "reason": hint.reason,
+        "aircraft_in_range": aircraft_in_range,
+    }))
+    .into_response()
+}
+
+async fn get_runways(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.runways.read().await.in_use())
+}
+
+#[allow(clippy::collapsible_if)]
 async fn get_opensky_token(state: &AppState) -> Option<String> {
     let client_id = state.opensky_client_id.as_ref()?;
     let client_secret = state.opensky_client_secret.as_ref()?;
@@ -340,90 +1409,75 @@ async fn get_opensky_token(state: &AppState) -> Option<String> {
     }
 }
 
-fn svg_to_epd_bin(svg: &str, opt: &usvg::Options) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let tree = Tree::from_str(svg, opt)?;
-    let pixmap_size = tree.size();
-    let mut pixmap = Pixmap::new(pixmap_size.width() as u32, pixmap_size.height() as u32).unwrap();
-    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
-
-    let dithered = apply_floyd_steinberg(pixmap);
-    Ok(pixmap_to_epd_bin(dithered))
+fn layers_to_epd_bin(
+    layers: &SvgLayers,
+    mode: DitherMode,
+    profile: &PanelProfile,
+    opt: &usvg::Options,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let dithered = render_dithered(layers, mode, profile, opt)?;
+    Ok(pixmap_to_epd_bin(&dithered, profile))
 }
 
-fn get_epd_color(rgb: [u8; 3]) -> u8 {
-    match rgb {
-        [0, 0, 0] => 0,       // BLACK
-        [255, 255, 255] => 1, // WHITE
-        [255, 255, 0] => 2,   // YELLOW
-        [255, 0, 0] => 3,     // RED
-        [0, 0, 255] => 5,     // BLUE
-        [0, 255, 0] => 6,     // GREEN
-        _ => 1,               // Default to WHITE
-    }
+fn layers_to_native_indices(
+    layers: &SvgLayers,
+    mode: DitherMode,
+    profile: &PanelProfile,
+    opt: &usvg::Options,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let dithered = render_dithered(layers, mode, profile, opt)?;
+    Ok(panel::native_indices(&dithered, profile))
 }
 
-fn pixmap_to_epd_bin(pixmap: Pixmap) -> Vec<u8> {
-    let src_w = pixmap.width() as usize;
-    let src_h = pixmap.height() as usize;
-
-    // The EPD is 1200x1600 total, split into two 600x1600 vertical strips.
-    const TARGET_W: usize = 1200;
-    const TARGET_H: usize = 1600;
-    const HALF_W: usize = TARGET_W / 2;
-
-    let mut buffer = vec![0u8; TARGET_W * TARGET_H / 2];
-    let half_buffer_len = buffer.len() / 2;
-
-    let pixels = pixmap.pixels();
-
-    for y_new in 0..TARGET_H {
-        for x_new in 0..TARGET_W {
-            // Rotate 90 degrees clockwise to fit 1600x1200 landscape into 1200x1600 portrait
-            // x_new = (src_h - 1) - y_old  => y_old = (src_h - 1) - x_new
-            // y_new = x_old               => x_old = y_new
-            let x_old = y_new;
-            let y_old = (src_h - 1).saturating_sub(x_new);
-
-            if x_old < src_w && y_old < src_h {
-                let p_idx = y_old * src_w + x_old;
-                let p = pixels[p_idx].demultiply();
-                let color = get_epd_color([p.red(), p.green(), p.blue()]);
-
-                let (tx, offset) = if x_new < HALF_W {
-                    (x_new, 0)
-                } else {
-                    (x_new - HALF_W, half_buffer_len)
-                };
-
-                let pixel_idx = tx + y_new * HALF_W;
-                let byte_pos = offset + pixel_idx / 2;
+fn layers_to_dithered_png(
+    layers: &SvgLayers,
+    mode: DitherMode,
+    profile: &PanelProfile,
+    opt: &usvg::Options,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let dithered = render_dithered(layers, mode, profile, opt)?;
+    Ok(dithered.encode_png()?)
+}
 
-                if (pixel_idx & 1) == 0 {
-                    buffer[byte_pos] |= color << 4;
-                } else {
-                    buffer[byte_pos] |= color;
-                }
-            }
+/// Renders the layers at the panel's canvas size and dithers them against its palette.
+fn render_dithered(
+    layers: &SvgLayers,
+    mode: DitherMode,
+    profile: &PanelProfile,
+    opt: &usvg::Options,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let size = profile.canvas_size();
+    let palette = profile.palette;
+    match mode {
+        DitherMode::Full => {
+            let pixmap = render_pixmap_sized(&layers.to_svg(), size, opt)?;
+            Ok(apply_floyd_steinberg(pixmap, palette))
+        }
+        DitherMode::Layered => {
+            let background = render_pixmap_sized(&layers.background_svg(), size, opt)?;
+            let mut pixmap = apply_floyd_steinberg(background, palette);
+            let overlay = render_pixmap_sized(&layers.overlay_svg(), size, opt)?;
+            composite_snapped(&mut pixmap, &overlay, palette);
+            Ok(pixmap)
         }
     }
-
-    buffer
 }
 
-fn svg_to_dithered_png(
-    svg: &str,
-    opt: &usvg::Options,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let tree = Tree::from_str(svg, opt)?;
-    let pixmap_size = tree.size();
-    let mut pixmap = Pixmap::new(pixmap_size.width() as u32, pixmap_size.height() as u32).unwrap();
-    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
-
-    let dithered = apply_floyd_steinberg(pixmap);
-    Ok(dithered.encode_png()?)
+/// Draws the covered pixels of `overlay` on top of `pixmap`, snapped to the exact palette colour.
+/// Pixels the overlay covers less than half are left to the layer below.
+fn composite_snapped(pixmap: &mut Pixmap, overlay: &Pixmap, palette: &[PaletteEntry]) {
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(overlay.pixels()) {
+        if src.alpha() < 128 {
+            continue;
+        }
+        let p = src.demultiply();
+        let rgb = [p.red() as f32, p.green() as f32, p.blue() as f32];
+        let [r, g, b] = find_closest_color(rgb, palette);
+        *dst = tiny_skia::ColorU8::from_rgba(r, g, b, 255).premultiply();
+    }
 }
 
-fn apply_floyd_steinberg(pixmap: Pixmap) -> Pixmap {
+fn apply_floyd_steinberg(pixmap: Pixmap, palette: &[PaletteEntry]) -> Pixmap {
     let width = pixmap.width() as usize;
     let height = pixmap.height() as usize;
     let mut data = vec![[0.0f32; 3]; width * height];
@@ -450,7 +1504,7 @@ fn apply_floyd_steinberg(pixmap: Pixmap) -> Pixmap {
                 current_pixel[2].clamp(0.0, 255.0),
             ];
 
-            let new_rgb = find_closest_color(old_rgb);
+            let new_rgb = find_closest_color(old_rgb, palette);
 
             // Update the buffer with the final quantized color
             data[idx] = [new_rgb[0] as f32, new_rgb[1] as f32, new_rgb[2] as f32];
@@ -491,11 +1545,11 @@ fn apply_floyd_steinberg(pixmap: Pixmap) -> Pixmap {
     out_pixmap
 }
 
-fn find_closest_color(rgb: [f32; 3]) -> [u8; 3] {
+fn find_closest_color(rgb: [f32; 3], palette: &[PaletteEntry]) -> [u8; 3] {
     let mut min_dist = f32::MAX;
-    let mut closest = PALETTE[0];
+    let mut closest = palette[0].rgb;
 
-    for color in PALETTE {
+    for &PaletteEntry { rgb: color, .. } in palette {
         let dist = (rgb[0] - color[0] as f32).powi(2)
             + (rgb[1] - color[1] as f32).powi(2)
             + (rgb[2] - color[2] as f32).powi(2);
@@ -514,23 +1568,52 @@ fn distribute_error(pixel: &mut [f32; 3], err: [f32; 3], factor: f32) {
 }
 
 fn svg_to_png(svg: &str, opt: &usvg::Options) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Ok(render_pixmap(svg, opt)?.encode_png()?)
+}
+
+fn render_pixmap(svg: &str, opt: &usvg::Options) -> Result<Pixmap, Box<dyn std::error::Error>> {
     let tree = Tree::from_str(svg, opt)?;
 
     let pixmap_size = tree.size();
     let mut pixmap = Pixmap::new(pixmap_size.width() as u32, pixmap_size.height() as u32).unwrap();
     resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
 
-    Ok(pixmap.encode_png()?)
+    Ok(pixmap)
 }
 
-async fn fetch_closest_flight(
+/// Renders `svg` scaled to fit a canvas of `size`, centred on white.
+fn render_pixmap_sized(
+    svg: &str,
+    size: (usize, usize),
+    opt: &usvg::Options,
+) -> Result<Pixmap, Box<dyn std::error::Error>> {
+    let tree = Tree::from_str(svg, opt)?;
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    let tree_size = tree.size();
+    let scale = (width / tree_size.width()).min(height / tree_size.height());
+    let dx = (width - tree_size.width() * scale) / 2.0;
+    let dy = (height - tree_size.height() * scale) / 2.0;
+
+    let mut pixmap = Pixmap::new(size.0 as u32, size.1 as u32).unwrap();
+    if dx > 0.0 || dy > 0.0 {
+        pixmap.fill(tiny_skia::Color::WHITE);
+    }
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(dx, dy);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    Ok(pixmap)
+}
+
+/// Fetches every aircraft in the box around the observer.
+async fn fetch_traffic(
     client: &reqwest::Client,
     token: Option<&str>,
-) -> Result<Option<Flight>, Box<dyn std::error::Error>> {
-    let lamin = LAT - BOX_SIZE;
-    let lamax = LAT + BOX_SIZE;
-    let lomin = LON - BOX_SIZE;
-    let lomax = LON + BOX_SIZE;
+    observer: (f64, f64),
+) -> Result<Vec<Aircraft>, Box<dyn std::error::Error>> {
+    let lamin = observer.0 - BOX_SIZE;
+    let lamax = observer.0 + BOX_SIZE;
+    let lomin = observer.1 - BOX_SIZE;
+    let lomax = observer.1 + BOX_SIZE;
 
     let url = format!(
         "https://opensky-network.org/api/states/all?lamin={}&lomin={}&lamax={}&lomax={}",
@@ -544,76 +1627,233 @@ async fn fetch_closest_flight(
     }
     let resp: OpenSkyResponse = rb.send().await?.json().await?;
 
-    let states = match resp.states {
-        Some(s) => s,
-        None => return Ok(None),
-    };
+    Ok(resp
+        .states
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|state| Aircraft::from_state(state, observer))
+        .collect())
+}
 
-    let mut flights = Vec::new();
-    for state in states {
-        let icao24 = state[0].as_str().unwrap_or_default().to_string();
-        let callsign = state[1].as_str().unwrap_or_default().trim().to_string();
-        let longitude = state[5].as_f64();
-        let latitude = state[6].as_f64();
-        let baro_altitude = state[7].as_f64();
+/// Whether the aircraft is low and close enough to be shown.
+#[allow(clippy::collapsible_if)]
+fn in_range(aircraft: &Aircraft) -> bool {
+    // Filter out flights above the altitude limit
+    if let Some(alt) = aircraft.baro_altitude {
+        if alt > MAX_ALTITUDE_METERS {
+            return false;
+        }
+    }
+    aircraft.distance <= MAX_DISTANCE_KM
+}
 
-        if let (Some(lat), Some(lon)) = (latitude, longitude) {
-            // Filter out flights above the altitude limit
-            if let Some(alt) = baro_altitude {
-                if alt > MAX_ALTITUDE_METERS {
-                    continue;
-                }
-            }
+fn closest_flight(traffic: &[Aircraft]) -> Option<&Aircraft> {
+    traffic
+        .iter()
+        .filter(|a| in_range(a))
+        .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+}
 
-            let distance = haversine_distance(LAT, LON, lat, lon);
-            if distance > MAX_DISTANCE_KM {
-                continue;
-            }
+/// The aircraft to show: the closest in range, unless aircraft with an alert are in range and
+/// at most `priority_km` farther, in which case the one with the most urgent alert.
+fn select_flight(
+    traffic: &[Aircraft],
+    alert: impl Fn(&Aircraft) -> Option<Alert>,
+    priority_km: f64,
+) -> Option<&Aircraft> {
+    let closest = closest_flight(traffic)?;
+    let alerted = traffic
+        .iter()
+        .filter(|a| in_range(a) && a.distance <= closest.distance + priority_km)
+        .filter_map(|a| Some((alert(a)?, a)))
+        .min_by(|(alert_a, a), (alert_b, b)| {
+            alert_a.cmp(alert_b).then(a.distance.total_cmp(&b.distance))
+        });
+    Some(alerted.map_or(closest, |(_, a)| a))
+}
 
-            flights.push(Flight {
-                icao24,
-                callsign,
-                flight_number: None,
-                aircraft_type: None,
-                distance,
-                photo_url: None,
-                photo_base64: None,
-                origin_iata: None,
-                origin_name: None,
-                dest_iata: None,
-                dest_name: None,
-            });
-        }
+/// Everything known about `aircraft` but the photo itself, which `download_photo` adds.
+async fn fetch_flight_details(state: &AppState, aircraft: &Aircraft) -> Flight {
+    let client = &state.client;
+    let airlines = &state.airlines;
+    let mut flight = Flight {
+        icao24: aircraft.icao24.clone(),
+        callsign: aircraft.callsign.clone(),
+        flight_number: None,
+        aircraft_type: None,
+        registration: None,
+        type_code: None,
+        manufacturer: None,
+        operator: None,
+        owner: None,
+        latitude: aircraft.latitude,
+        longitude: aircraft.longitude,
+        distance: aircraft.distance,
+        bearing: aircraft.bearing,
+        baro_altitude: aircraft.baro_altitude,
+        geo_altitude: aircraft.geo_altitude,
+        ground_speed: aircraft.velocity,
+        track: aircraft.true_track,
+        vertical_rate: aircraft.vertical_rate,
+        squawk: aircraft.squawk.clone(),
+        airline: airlines.lookup(&aircraft.callsign).cloned(),
+        airline_logo: airlines.logo(&aircraft.callsign),
+        photo_url: None,
+        photo_base64: None,
+        origin_iata: None,
+        origin_name: None,
+        dest_iata: None,
+        dest_name: None,
+        origin: None,
+        destination: None,
+        eta: None,
+        movement: None,
+        alert: None,
+    };
+
+    flight.photo_url = fetch_photo_url(client, &flight.icao24).await;
+    if let Some((origin, destination)) = state.airports.route(&flight.callsign) {
+        flight.origin_iata = Some(origin.iata.clone().unwrap_or_else(|| origin.icao.clone()));
+        flight.origin_name = Some(origin.city.clone().unwrap_or_else(|| origin.name.clone()));
+        flight.dest_iata = Some(
+            destination
+                .iata
+                .clone()
+                .unwrap_or_else(|| destination.icao.clone()),
+        );
+        flight.dest_name = Some(
+            destination
+                .city
+                .clone()
+                .unwrap_or_else(|| destination.name.clone()),
+        );
+        flight.origin = Some(origin.clone());
+        flight.destination = Some(destination.clone());
+    } else if let Some(route) = fetch_route(client, &flight.callsign).await {
+        flight.origin = (state.airports.get(&route.origin.icao_code).cloned())
+            .or_else(|| route.origin.to_airport());
+        flight.destination = (state.airports.get(&route.destination.icao_code).cloned())
+            .or_else(|| route.destination.to_airport());
+        flight.origin_iata = Some(route.origin.iata_code);
+        flight.origin_name = Some(route.origin.municipality);
+        flight.dest_iata = Some(route.destination.iata_code);
+        flight.dest_name = Some(route.destination.municipality);
+        flight.flight_number = route.callsign_iata;
+    }
+    flight.eta = flight
+        .progress()
+        .zip(flight.ground_speed)
+        .and_then(|(progress, speed)| progress.time_remaining(speed))
+        .map(|remaining| local_now(&state.config) + remaining);
+
+    if let Some(home) = &state.config.home_airport {
+        let codes = |code: &Option<String>, airport: &Option<Airport>| {
+            let mut codes: Vec<String> = code.iter().cloned().collect();
+            codes.extend(airport.iter().map(|a| a.icao.clone()));
+            codes
+        };
+        let origin = codes(&flight.origin_iata, &flight.origin);
+        let destination = codes(&flight.dest_iata, &flight.destination);
+        // The current rate and the trend over the last few minutes.
+        let trend = state.tracks.read().await.climb_rate(&flight.icao24);
+        let climb_rate = match (flight.vertical_rate, trend) {
+            (Some(rate), Some(trend)) => Some((rate + trend) / 2.0),
+            (rate, trend) => rate.or(trend),
+        };
+        flight.movement = movement::classify(
+            home,
+            &Observation {
+                origin: origin.iter().map(String::as_str).collect(),
+                destination: destination.iter().map(String::as_str).collect(),
+                position: (flight.latitude, flight.longitude),
+                altitude: flight.baro_altitude.or(flight.geo_altitude),
+                climb_rate,
+            },
+        );
     }
 
-    flights.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+    let record = match aircraft_record(state, &flight.icao24).await {
+        Some(record) => Some(record),
+        None => fetch_aircraft_info(client, &flight.icao24)
+            .await
+            .map(|aircraft| AircraftRecord {
+                registration: aircraft.registration,
+                type_code: aircraft.icao_type,
+                manufacturer: aircraft.manufacturer,
+                model: Some(aircraft.aircraft_type),
+                operator: None,
+                owner: aircraft.registered_owner,
+            }),
+    };
+    if let Some(record) = record {
+        flight.aircraft_type = record.model.or(record.type_code.clone());
+        flight.registration = record.registration;
+        flight.type_code = record.type_code;
+        flight.manufacturer = record.manufacturer;
+        flight.operator = record.operator;
+        flight.owner = record.owner;
+    }
+    flight.alert = state.alerts.check(aircraft, flight.type_code.as_deref());
+    flight
+}
+
+/// Downloads the photo at `flight.photo_url` as a data URI for resvg.
+#[allow(clippy::collapsible_if)]
+async fn download_photo(client: &reqwest::Client, flight: &mut Flight) {
+    let Some(url) = &flight.photo_url else {
+        return;
+    };
+    info!("Fetching plane photo from: {}", url);
+    if let Ok(resp) = client.get(url).send().await {
+        if let Ok(bytes) = resp.bytes().await {
+            let b64 = general_purpose::STANDARD.encode(bytes);
+            flight.photo_base64 = Some(format!("data:image/jpeg;base64,{}", b64));
+        }
+    }
+}
 
-    if let Some(mut flight) = flights.first().cloned() {
-        if let Some(url) = fetch_photo_url(client, &flight.icao24).await {
-            flight.photo_url = Some(url.clone());
-            // Fetch the image and convert to base64 for resvg
-            info!("Fetching plane photo from: {}", url);
-            if let Ok(resp) = client.get(url).send().await {
-                if let Ok(bytes) = resp.bytes().await {
-                    let b64 = general_purpose::STANDARD.encode(bytes);
-                    flight.photo_base64 = Some(format!("data:image/jpeg;base64,{}", b64));
+/// `icao24` in the local aircraft database, reloading it first if the file has changed. A file
+/// that can't be read is logged and the previous table kept until the file changes again.
+/// Lookups while another one is reloading use the previous table.
+async fn aircraft_record(state: &AppState, icao24: &str) -> Option<AircraftRecord> {
+    if let Ok(_reloading) = state.aircraft_db_reload.try_lock() {
+        // Checked again with the lock held, in case a reload has just finished.
+        let stale = state
+            .aircraft_db
+            .read()
+            .await
+            .as_ref()
+            .filter(|db| db.is_stale())
+            .map(|db| (db.path().to_path_buf(), db.file_modified()));
+        if let Some((path, modified)) = stale {
+            let error = match tokio::task::spawn_blocking(move || AircraftDb::load(&path)).await {
+                Ok(Ok(db)) => {
+                    info!(
+                        "Reloaded {} aircraft from {}",
+                        db.len(),
+                        db.path().display()
+                    );
+                    *state.aircraft_db.write().await = Some(db);
+                    None
+                }
+                Ok(Err(e)) => Some(e),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(e) = error {
+                error!("Error reloading aircraft database: {}", e);
+                if let Some(db) = state.aircraft_db.write().await.as_mut() {
+                    db.reload_failed(modified);
                 }
             }
         }
-        if let Some(route) = fetch_route(client, &flight.callsign).await {
-            flight.origin_iata = Some(route.origin.iata_code);
-            flight.origin_name = Some(route.origin.municipality);
-            flight.dest_iata = Some(route.destination.iata_code);
-            flight.dest_name = Some(route.destination.municipality);
-            flight.flight_number = route.callsign_iata;
-        }
-        if let Some(aircraft) = fetch_aircraft_info(client, &flight.icao24).await {
-            flight.aircraft_type = Some(aircraft.aircraft_type);
-        }
-        Ok(Some(flight))
-    } else {
-        Ok(None)
     }
+    state
+        .aircraft_db
+        .read()
+        .await
+        .as_ref()?
+        .get(icao24)
+        .cloned()
 }
 
 async fn fetch_route(client: &reqwest::Client, callsign: &str) -> Option<AdsbdbFlightRoute> {
@@ -640,142 +1880,182 @@ async fn fetch_photo_url(client: &reqwest::Client, icao24: &str) -> Option<Strin
     resp.photos.first().map(|p| p.thumbnail_large.src.clone())
 }
 
-fn render_svg(flight: &Flight) -> String {
-    let callsign = if flight.callsign.is_empty() {
-        "Unknown"
-    } else {
-        &flight.callsign
-    };
-
-    let aircraft_type = flight.aircraft_type.as_deref().unwrap_or("Unknown");
-    let flight_number = flight.flight_number.as_deref().unwrap_or("---");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let origin_iata = flight.origin_iata.as_deref().unwrap_or("???");
-    let origin_name = flight.origin_name.as_deref().unwrap_or("Unknown Origin");
-    let dest_iata = flight.dest_iata.as_deref().unwrap_or("???");
-    let dest_name = flight.dest_name.as_deref().unwrap_or("Unknown Destination");
+    fn test_options() -> usvg::Options<'static> {
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_font_data(FONT_DATA.to_vec());
+        usvg::Options {
+            fontdb: Arc::new(fontdb),
+            ..Default::default()
+        }
+    }
 
-    let photo_data = flight.photo_base64.as_deref().unwrap_or("");
-    let has_photo = !photo_data.is_empty();
+    #[test]
+    fn test_layered_dither_keeps_overlay_crisp() {
+        // A mid-grey photo stand-in that dithers into a mix of palette colours.
+        let layers = SvgLayers {
+            background: "<rect width='1600' height='1200' fill='#808080' />".to_string(),
+            overlay: "<rect width='1600' height='160' fill='white' />\
+                      <text x='100' y='120' font-family='Google Sans' font-size='100' fill='#000000'>SWR123</text>"
+                .to_string(),
+        };
+        let profile = panel::find_profile(panel::DEFAULT_PANEL).unwrap();
+        let pixmap =
+            render_dithered(&layers, DitherMode::Layered, profile, &test_options()).unwrap();
+
+        let width = pixmap.width() as usize;
+        let header = &pixmap.pixels()[..width * 160];
+        let mut black = 0;
+        for p in header {
+            let rgb = [p.red(), p.green(), p.blue()];
+            assert!(rgb == [0, 0, 0] || rgb == [255, 255, 255], "{:?}", rgb);
+            if rgb == [0, 0, 0] {
+                black += 1;
+            }
+        }
+        assert!(black > 0, "text was not drawn");
 
-    let image_layer = if has_photo {
-        format!(
-            r#"<image id="bg" href="{}" width="1600" height="1200" preserveAspectRatio="xMidYMid meet" />"#,
-            photo_data
-        )
-    } else {
-        "".to_string()
-    };
+        // The photo area is still dithered.
+        let body = &pixmap.pixels()[width * 600..width * 601];
+        assert!(body.iter().any(|p| p.red() == 0));
+        assert!(body.iter().any(|p| p.red() == 255));
+    }
 
-    format!(
-        r#"<svg width='1600' height='1200' viewBox='0 0 1600 1200' xmlns='http://www.w3.org/2000/svg'>
-  <rect width='1600' height='1200' fill='white' />
-  {image_layer}
-
-  <!-- Overlay Boxes -->
-  <rect x='0' y='0' width='1600' height='160' fill='white' fill-opacity='1.0' />
-  <rect x='0' y='1040' width='1600' height='160' fill='white' fill-opacity='1.0' />
-
-  <!-- Route (Top) -->
-  <g transform='translate(0, 105)'>
-    <!-- Origin -->
-    <g transform='translate(400, 0)'>
-      <text x='0' y='0' font-family='Google Sans, sans-serif' font-size='100' text-anchor='middle' fill='#000000' font-weight='bold'>{origin_iata}</text>
-      <text x='0' y='45' font-family='Google Sans, sans-serif' font-size='35' text-anchor='middle' fill='#000000'>{origin_name}</text>
-    </g>
-
-    <!-- Arrow -->
-    <text x='800' y='0' font-family='Google Sans, sans-serif' font-size='80' text-anchor='middle' fill='#000000' font-weight='bold'>→</text>
-
-    <!-- Destination -->
-    <g transform='translate(1200, 0)'>
-      <text x='0' y='0' font-family='Google Sans, sans-serif' font-size='100' text-anchor='middle' fill='#000000' font-weight='bold'>{dest_iata}</text>
-      <text x='0' y='45' font-family='Google Sans, sans-serif' font-size='35' text-anchor='middle' fill='#000000'>{dest_name}</text>
-    </g>
-  </g>
-
-  <!-- Info Row (Bottom) -->
-  <g transform='translate(0, 1090)'>
-    <!-- Callsign -->
-    <g transform='translate(200, 0)'>
-      <text x='0' y='0' font-family='Google Sans, sans-serif' font-size='40' text-anchor='middle' fill='#000000'>CALLSIGN</text>
-      <text x='0' y='85' font-family='Google Sans, sans-serif' font-size='90' text-anchor='middle' fill='#000000' font-weight='bold'>{callsign}</text>
-    </g>
-
-    <!-- Flight Number -->
-    <g transform='translate(800, 0)'>
-      <text x='0' y='0' font-family='Google Sans, sans-serif' font-size='40' text-anchor='middle' fill='#000000'>FLIGHT</text>
-      <text x='0' y='85' font-family='Google Sans, sans-serif' font-size='90' text-anchor='middle' fill='#000000' font-weight='bold'>{flight_number}</text>
-    </g>
-
-    <!-- Aircraft Type -->
-    <g transform='translate(1400, 0)'>
-      <text x='0' y='0' font-family='Google Sans, sans-serif' font-size='40' text-anchor='middle' fill='#000000'>AIRCRAFT TYPE</text>
-      <text x='0' y='85' font-family='Google Sans, sans-serif' font-size='70' text-anchor='middle' fill='#000000' font-weight='bold'>{aircraft_type}</text>
-    </g>
-  </g>
-</svg>"#,
-        image_layer = image_layer,
-        origin_iata = origin_iata,
-        origin_name = origin_name,
-        dest_iata = dest_iata,
-        dest_name = dest_name,
-        callsign = callsign,
-        flight_number = flight_number,
-        aircraft_type = aircraft_type
-    )
-}
+    #[test]
+    fn test_image_params_from_query() {
+        let uri: axum::http::Uri =
+            "/image.bin?panel=mono-7in5&rotation=180&mirror=vertical&strips=none&dither=layered"
+                .parse()
+                .unwrap();
+        let Query(params) = Query::<ImageParams>::try_from_uri(&uri).unwrap();
+        assert_eq!(params.panel.as_deref(), Some("mono-7in5"));
+        assert_eq!(params.rotation, Some(Rotation::Deg180));
+        assert_eq!(params.mirror, Some(Mirror::Vertical));
+        assert_eq!(params.strips, Some(StripLayout::Single));
+        assert_eq!(params.dither, DitherMode::Layered);
+
+        assert_eq!(params.compression, None);
+
+        let uri: axum::http::Uri = "/image.bin?compression=rle".parse().unwrap();
+        let Query(params) = Query::<ImageParams>::try_from_uri(&uri).unwrap();
+        assert_eq!(params.compression, Some(Compression::Rle));
+
+        let uri: axum::http::Uri = "/image.bin?rotation=45".parse().unwrap();
+        assert!(Query::<ImageParams>::try_from_uri(&uri).is_err());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_adsbdb_route() {
+        let json = r#"{"response":{"flightroute":{"callsign":"SWR8VP","callsign_iata":"LX319",
+            "origin":{"country_iso_name":"GB","country_name":"United Kingdom","elevation":83,
+                "iata_code":"LHR","icao_code":"EGLL","latitude":51.4706,"longitude":-0.461941,
+                "municipality":"London","name":"London Heathrow Airport"},
+            "destination":{"iata_code":"ZRH","icao_code":"LSZH","municipality":"Zurich"}}}}"#;
+        let response: AdsbdbResponse = serde_json::from_str(json).unwrap();
+        let route = response.response.flightroute.unwrap();
+        let origin = route.origin.to_airport().unwrap();
+        assert_eq!(origin.name, "London Heathrow Airport");
+        assert_eq!(origin.country.as_deref(), Some("GB"));
+        assert_eq!((origin.latitude, origin.longitude), (51.4706, -0.461941));
+        // Not without coordinates.
+        assert_eq!(route.destination.to_airport(), None);
+    }
 
     #[test]
-    fn test_haversine_distance() {
-        // Distance between two points in Zurich
-        let lat1 = 47.3769;
-        let lon1 = 8.5417;
-        let lat2 = 47.3780;
-        let lon2 = 8.5400;
-        let dist = haversine_distance(lat1, lon1, lat2, lon2);
-        // Approx 0.17 km
-        assert!(dist > 0.1 && dist < 0.3);
+    fn test_select_flight() {
+        let traffic = [
+            Aircraft::test("close", 2.0),
+            Aircraft::test("rare", 3.5),
+            Aircraft::test("military", 3.0),
+            Aircraft::test("emergency", 5.0),
+        ];
+        let alert = |a: &Aircraft| match a.icao24.as_str() {
+            "rare" => Some(Alert::Rare),
+            "military" => Some(Alert::Military),
+            "emergency" => Some(Alert::Emergency),
+            _ => None,
+        };
+        let selected = |priority_km| {
+            select_flight(&traffic, alert, priority_km)
+                .unwrap()
+                .icao24
+                .as_str()
+        };
+        assert_eq!(selected(0.0), "close");
+        assert_eq!(selected(2.0), "military");
+        assert_eq!(selected(3.0), "emergency");
+        assert_eq!(
+            select_flight(&traffic[..1], alert, 2.0).unwrap().icao24,
+            "close"
+        );
+        assert!(select_flight(&[], alert, 2.0).is_none());
     }
 
     #[test]
-    fn test_render_svg() {
-        let flight = Flight {
-            icao24: "test".to_string(),
-            callsign: "TEST123".to_string(),
-            flight_number: Some("LX123".to_string()),
-            aircraft_type: Some("Airbus A320".to_string()),
-            distance: 5.0,
-            photo_url: Some("http://example.com/photo.jpg".to_string()),
-            photo_base64: Some("data:image/jpeg;base64,VEVTVA==".to_string()),
-            origin_iata: Some("WAW".to_string()),
-            origin_name: Some("Warsaw".to_string()),
-            dest_iata: Some("ZRH".to_string()),
-            dest_name: Some("Zurich".to_string()),
+    fn test_accepts_rle() {
+        let accepts = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::ACCEPT_ENCODING,
+                HeaderValue::from_str(value).unwrap(),
+            );
+            accepts_rle(&headers)
         };
-        let svg = render_svg(&flight);
-        assert!(svg.contains("TEST123"));
-        assert!(svg.contains("LX123"));
-        assert!(svg.contains("WAW"));
-        assert!(svg.contains("ZRH"));
-        assert!(svg.contains("Airbus A320"));
-        assert!(svg.contains("data:image/jpeg;base64,VEVTVA=="));
+        assert!(accepts("x-epd-rle"));
+        assert!(accepts("gzip, x-epd-rle;q=0.5"));
+        assert!(!accepts("gzip, deflate"));
+        assert!(!accepts("x-epd-rle; q=0"));
+        assert!(!accepts("x-epd-rle;q=0.0"));
+        assert!(!accepts("x-epd-rle; Q = 0.000"));
+        assert!(accepts("X-EPD-RLE"));
+        assert!(accepts("X-Epd-Rle; q=0.001"));
+        assert!(!accepts_rle(&HeaderMap::new()));
     }
 
     #[test]
-    fn test_render_no_flight_svg() {
-        let svg = render_no_flight_svg();
-        assert!(svg.contains("rect width='1600' height='1200' fill='white'"));
+    fn test_conditional_requests() {
+        let etag = content_etag(&["bin", "<svg/>", "spectra6"]);
+        assert_eq!(etag, content_etag(&["bin", "<svg/>", "spectra6"]));
+        assert_ne!(etag, content_etag(&["bin", "<svg />", "spectra6"]));
+        // Parts are length-delimited, so moving text between them changes the tag.
+        assert_ne!(etag, content_etag(&["bin<svg/>", "", "spectra6"]));
+        // Pinned, as devices keep tags across server updates.
+        assert_eq!(etag, "\"c1361b6a\"");
+
+        let if_none_match = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+            is_not_modified(&headers, &etag)
+        };
+        assert!(if_none_match(&etag));
+        assert!(if_none_match(&format!("\"abc\", W/{}", etag)));
+        assert!(if_none_match("*"));
+        assert!(!if_none_match("\"abc\""));
+        assert!(!is_not_modified(&HeaderMap::new(), &etag));
+
+        let resp = not_modified(&etag);
+        assert_eq!(resp.status(), 304);
+        assert_eq!(resp.headers()[header::ETAG], etag.as_str());
     }
-}
 
-fn render_no_flight_svg() -> String {
-    r#"<svg width='1600' height='1200' viewBox='0 0 1600 1200' xmlns='http://www.w3.org/2000/svg'>
-  <rect width='1600' height='1200' fill='white' />
-</svg>"#
-        .to_string()
+    #[test]
+    fn test_output_key() {
+        let profile = panel::find_profile("spectra6-13in3").unwrap();
+        // Pinned like the tag itself.
+        assert_eq!(
+            output_key(profile, DitherMode::Layered, Some(Compression::Rle)),
+            "spectra6-13in3 1200x1600 4bpp rotation=90 mirror=none strips=vertical-halves dither=layered compression=rle"
+        );
+        let rotated = PanelProfile {
+            rotation: panel::Rotation::Deg270,
+            ..profile.clone()
+        };
+        assert_ne!(
+            output_key(profile, DitherMode::Full, None),
+            output_key(&rotated, DitherMode::Full, None)
+        );
+    }
 }
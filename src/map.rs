@@ -0,0 +1,593 @@
+//! The radar inset: a small map of the area around the observer, drawn in SVG.
+//!
+//! Positions are projected with `geo::local_offset_km`, which is accurate enough for the few tens
+//! of km a panel can show.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::MAX_DISTANCE_KM;
+use crate::basemap::Class;
+use crate::geo::local_offset_km;
+use crate::svg::SvgWriter;
+
+const FONT_FAMILY: &str = "Google Sans, sans-serif";
+
+/// An aircraft silhouette pointing north, 20 units long.
+const AIRCRAFT_PATH: &str = "M0,-10 L1.5,-7 L1.5,-2 L10,3 L10,5 L1.5,2.5 L1.5,7 L4,9 L4,10.5 L0,9.5 L-4,10.5 L-4,9 L-1.5,7 L-1.5,2.5 L-10,5 L-10,3 L-1.5,-2 L-1.5,-7 Z";
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapConfig {
+    /// Draw the inset on layouts that have room for it.
+    pub inset: bool,
+    /// Radius of the area shown, in km.
+    pub range_km: f64,
+    /// GeoJSON file of landmarks to draw and label, e.g. runways.
+    pub landmarks: Option<PathBuf>,
+    /// GeoJSON file of water, roads and the like to draw under everything else.
+    pub basemap: Option<PathBuf>,
+    /// How far back the aircraft's trail goes.
+    pub trail_minutes: u32,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        MapConfig {
+            inset: false,
+            range_km: MAX_DISTANCE_KM,
+            landmarks: None,
+            basemap: None,
+            trail_minutes: 5,
+        }
+    }
+}
+
+/// A latitude and longitude in degrees.
+pub type LatLon = (f64, f64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Points(Vec<LatLon>),
+    Lines(Vec<Vec<LatLon>>),
+    /// Polygon rings, holes included, drawn with the even-odd rule.
+    Area(Vec<Vec<LatLon>>),
+}
+
+/// A feature from a GeoJSON file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    /// The `name` property, used as the label of landmarks.
+    pub name: Option<String>,
+    /// How the feature is drawn on the basemap, `None` to leave it out.
+    pub class: Option<Class>,
+    pub shape: Shape,
+}
+
+/// GeoJSON positions are longitude first, optionally followed by an altitude.
+type Position = Vec<f64>;
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum GeoJson {
+    FeatureCollection { features: Vec<GeoJsonFeature> },
+    Feature(GeoJsonFeature),
+}
+
+#[derive(Deserialize)]
+struct GeoJsonFeature {
+    geometry: Option<Geometry>,
+    #[serde(default)]
+    properties: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+enum Geometry {
+    Point(Position),
+    MultiPoint(Vec<Position>),
+    LineString(Vec<Position>),
+    MultiLineString(Vec<Vec<Position>>),
+    Polygon(Vec<Vec<Position>>),
+    MultiPolygon(Vec<Vec<Vec<Position>>>),
+}
+
+fn lat_lon(position: &Position) -> Result<LatLon, String> {
+    match position.as_slice() {
+        [lon, lat, ..] => Ok((*lat, *lon)),
+        _ => Err(format!("invalid position {:?}", position)),
+    }
+}
+
+fn lat_lons(positions: &[Position]) -> Result<Vec<LatLon>, String> {
+    positions.iter().map(lat_lon).collect()
+}
+
+fn rings(rings: &[Vec<Position>]) -> Result<Vec<Vec<LatLon>>, String> {
+    rings.iter().map(|ring| lat_lons(ring)).collect()
+}
+
+impl Geometry {
+    fn shape(&self) -> Result<Shape, String> {
+        Ok(match self {
+            Geometry::Point(p) => Shape::Points(vec![lat_lon(p)?]),
+            Geometry::MultiPoint(ps) => Shape::Points(lat_lons(ps)?),
+            Geometry::LineString(line) => Shape::Lines(vec![lat_lons(line)?]),
+            Geometry::MultiLineString(lines) => Shape::Lines(rings(lines)?),
+            Geometry::Polygon(polygon) => Shape::Area(rings(polygon)?),
+            Geometry::MultiPolygon(polygons) => Shape::Area(
+                polygons
+                    .iter()
+                    .map(|p| rings(p))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat(),
+            ),
+        })
+    }
+}
+
+/// Parses a GeoJSON feature collection or single feature. Features without a geometry are
+/// skipped; geometry collections are not supported.
+pub fn parse_geojson(text: &str) -> Result<Vec<Feature>, String> {
+    let features = match serde_json::from_str(text).map_err(|e| e.to_string())? {
+        GeoJson::FeatureCollection { features } => features,
+        GeoJson::Feature(feature) => vec![feature],
+    };
+    features
+        .iter()
+        .filter_map(|feature| {
+            let geometry = feature.geometry.as_ref()?;
+            let properties = feature.properties.as_ref();
+            let name = properties
+                .and_then(|p| p.get("name"))
+                .and_then(|name| name.as_str())
+                .map(str::to_string);
+            let class = properties.and_then(Class::from_properties);
+            Some(geometry.shape().map(|shape| Feature { name, class, shape }))
+        })
+        .collect()
+}
+
+pub fn load_geojson(path: &Path) -> Result<Vec<Feature>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+    parse_geojson(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// An aircraft on the inset.
+#[derive(Debug, Clone)]
+pub struct Plot {
+    pub position: LatLon,
+    /// Track in degrees clockwise from north, which the icon points along.
+    pub track: Option<f64>,
+}
+
+/// What the inset shows, rendered at the size and place a layout asks for.
+#[derive(Debug, Clone)]
+pub struct Inset {
+    /// Whether layouts that have room for the inset should draw it.
+    pub enabled: bool,
+    pub observer: LatLon,
+    pub range_km: f64,
+    /// The aircraft on screen.
+    pub aircraft: Option<Plot>,
+    /// Where the aircraft on screen has been, oldest first.
+    pub trail: Vec<LatLon>,
+    /// Other aircraft nearby, drawn as dots.
+    pub others: Vec<LatLon>,
+    pub landmarks: Arc<Vec<Feature>>,
+    pub basemap: Arc<Vec<Feature>>,
+}
+
+impl Inset {
+    /// Position of `point` in pixels from the centre of an inset of `radius` pixels, x to the
+    /// east and y to the south.
+    pub fn project(&self, point: LatLon, radius: f64) -> (f64, f64) {
+        let (east, north) = local_offset_km(self.observer.0, self.observer.1, point.0, point.1);
+        let scale = radius / self.range_km;
+        (east * scale, 0.0 - north * scale)
+    }
+
+    /// `line` as SVG coordinates, leaving out points less than a pixel from the previous one.
+    fn points(&self, line: &[LatLon], radius: f64) -> String {
+        let mut kept: Vec<(f64, f64)> = Vec::with_capacity(line.len());
+        for (i, &point) in line.iter().enumerate() {
+            let (x, y) = self.project(point, radius);
+            let last = i + 1 == line.len();
+            if let Some(&(px, py)) = kept.last()
+                && !last
+                && (x - px).hypot(y - py) < 1.0
+            {
+                continue;
+            }
+            kept.push((x, y));
+        }
+        kept.iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether any part of `shape` may be within `radius` of the centre.
+    fn is_visible(&self, shape: &Shape, radius: f64) -> bool {
+        let visible = |points: &mut dyn Iterator<Item = &LatLon>| {
+            let (mut min_x, mut min_y, mut max_x, mut max_y) =
+                (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+            for &point in points {
+                let (x, y) = self.project(point, radius);
+                (min_x, min_y) = (min_x.min(x), min_y.min(y));
+                (max_x, max_y) = (max_x.max(x), max_y.max(y));
+            }
+            min_x <= radius && max_x >= -radius && min_y <= radius && max_y >= -radius
+        };
+        match shape {
+            Shape::Points(points) => visible(&mut points.iter()),
+            Shape::Lines(parts) | Shape::Area(parts) => visible(&mut parts.iter().flatten()),
+        }
+    }
+
+    /// The basemap features of each class, areas filled and lines stroked.
+    fn render_basemap(&self, svg: &mut SvgWriter, radius: f64) {
+        for class in Class::ALL {
+            let style = class.style();
+            let features = self
+                .basemap
+                .iter()
+                .filter(|f| f.class == Some(class) && self.is_visible(&f.shape, radius));
+            for feature in features {
+                match &feature.shape {
+                    Shape::Points(_) => {}
+                    Shape::Lines(lines) => {
+                        for line in lines {
+                            svg.element("polyline")
+                                .attr("points", self.points(line, radius))
+                                .attr("fill", "none")
+                                .attr("stroke", style.stroke)
+                                .attr("stroke-width", style.line_width)
+                                .attr("stroke-linejoin", "round")
+                                .attr("stroke-linecap", "round")
+                                .empty();
+                        }
+                    }
+                    Shape::Area(rings) => {
+                        let d: Vec<String> = rings
+                            .iter()
+                            .map(|ring| format!("M{}Z", self.points(ring, radius)))
+                            .collect();
+                        let element = svg
+                            .element("path")
+                            .attr("d", d.join(" "))
+                            .attr("fill", style.fill.unwrap_or("none"))
+                            .attr("fill-rule", "evenodd");
+                        match style.outline {
+                            Some(outline) => element
+                                .attr("stroke", outline)
+                                .attr("stroke-width", 1.5)
+                                .empty(),
+                            None => element.empty(),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The inset as SVG elements, a circle of `radius` pixels centred on (`cx`, `cy`).
+    pub fn render(&self, cx: f64, cy: f64, radius: f64) -> String {
+        let clip_id = format!("inset-clip-{:.0}-{:.0}", cx, cy);
+        let mut svg = SvgWriter::new();
+        svg.element("g")
+            .attr("transform", format!("translate({:.1}, {:.1})", cx, cy))
+            .open();
+        svg.element("defs").open();
+        svg.element("clipPath").attr("id", &clip_id).open();
+        svg.element("circle").attr("r", radius).empty();
+        svg.close("clipPath");
+        svg.close("defs");
+
+        svg.element("circle")
+            .attr("r", radius)
+            .attr("fill", "white")
+            .empty();
+        svg.element("g")
+            .attr("clip-path", format!("url(#{})", clip_id))
+            .open();
+        self.render_basemap(&mut svg, radius);
+        self.render_landmarks(&mut svg, radius);
+        if self.trail.len() > 1 {
+            svg.element("polyline")
+                .attr("points", self.points(&self.trail, radius))
+                .attr("fill", "none")
+                .attr("stroke", "#FF0000")
+                .attr("stroke-width", 4)
+                .attr("stroke-linejoin", "round")
+                .attr("stroke-linecap", "round")
+                .empty();
+        }
+        for &other in &self.others {
+            let (x, y) = self.project(other, radius);
+            svg.element("circle")
+                .attr("cx", format!("{:.1}", x))
+                .attr("cy", format!("{:.1}", y))
+                .attr("r", 5)
+                .attr("fill", "#000000")
+                .empty();
+        }
+        svg.close("g");
+
+        self.render_rose(&mut svg, radius);
+        svg.element("circle")
+            .attr("r", 6)
+            .attr("fill", "#0000FF")
+            .empty();
+        if let Some(aircraft) = &self.aircraft {
+            let (x, y) = self.project(aircraft.position, radius);
+            let size = (radius / 120.0).max(1.2);
+            match aircraft.track {
+                Some(track) => svg
+                    .element("path")
+                    .attr("d", AIRCRAFT_PATH)
+                    .attr(
+                        "transform",
+                        format!(
+                            "translate({:.1}, {:.1}) rotate({:.0}) scale({:.2})",
+                            x, y, track, size
+                        ),
+                    )
+                    .attr("fill", "#FF0000")
+                    .attr("stroke", "#000000")
+                    .attr("stroke-width", format!("{:.2}", 1.5 / size))
+                    .empty(),
+                None => svg
+                    .element("circle")
+                    .attr("cx", format!("{:.1}", x))
+                    .attr("cy", format!("{:.1}", y))
+                    .attr("r", format!("{:.1}", 6.0 * size))
+                    .attr("fill", "#FF0000")
+                    .attr("stroke", "#000000")
+                    .attr("stroke-width", 1.5)
+                    .empty(),
+            }
+        }
+        svg.close("g");
+        svg.finish()
+    }
+
+    fn render_landmarks(&self, svg: &mut SvgWriter, radius: f64) {
+        for landmark in self.landmarks.iter() {
+            let anchor = match &landmark.shape {
+                Shape::Points(points) => {
+                    for &point in points {
+                        let (x, y) = self.project(point, radius);
+                        svg.element("rect")
+                            .attr("x", format!("{:.1}", x - 4.0))
+                            .attr("y", format!("{:.1}", y - 4.0))
+                            .attr("width", 8)
+                            .attr("height", 8)
+                            .attr("fill", "#000000")
+                            .empty();
+                    }
+                    points.first().map(|&p| self.project(p, radius))
+                }
+                Shape::Lines(lines) => {
+                    for line in lines {
+                        svg.element("polyline")
+                            .attr("points", self.points(line, radius))
+                            .attr("fill", "none")
+                            .attr("stroke", "#000000")
+                            .attr("stroke-width", 3)
+                            .empty();
+                    }
+                    centroid(lines.iter().flatten().map(|&p| self.project(p, radius)))
+                }
+                Shape::Area(rings) => {
+                    let d: Vec<String> = rings
+                        .iter()
+                        .map(|ring| format!("M{}Z", self.points(ring, radius)))
+                        .collect();
+                    svg.element("path")
+                        .attr("d", d.join(" "))
+                        .attr("fill", "none")
+                        .attr("fill-rule", "evenodd")
+                        .attr("stroke", "#000000")
+                        .attr("stroke-width", 2)
+                        .empty();
+                    centroid(rings.iter().flatten().map(|&p| self.project(p, radius)))
+                }
+            };
+            if let (Some(name), Some((x, y))) = (&landmark.name, anchor) {
+                svg.element("text")
+                    .attr("x", format!("{:.1}", x))
+                    .attr("y", format!("{:.1}", y - 10.0))
+                    .attr("font-family", FONT_FAMILY)
+                    .attr("font-size", 20)
+                    .attr("text-anchor", "middle")
+                    .attr("fill", "#000000")
+                    .attr("stroke", "white")
+                    .attr("stroke-width", 4)
+                    .attr("paint-order", "stroke")
+                    .text(name);
+            }
+        }
+    }
+
+    /// The range ring with ticks every 45° and the cardinal points.
+    fn render_rose(&self, svg: &mut SvgWriter, radius: f64) {
+        svg.element("circle")
+            .attr("r", radius)
+            .attr("fill", "none")
+            .attr("stroke", "#000000")
+            .attr("stroke-width", 3)
+            .empty();
+        for i in 0..8 {
+            let angle = (i as f64 * 45.0).to_radians();
+            let length = if i % 2 == 0 { 16.0 } else { 8.0 };
+            let (sin, cos) = angle.sin_cos();
+            svg.element("line")
+                .attr("x1", format!("{:.1}", radius * sin))
+                .attr("y1", format!("{:.1}", -radius * cos))
+                .attr("x2", format!("{:.1}", (radius - length) * sin))
+                .attr("y2", format!("{:.1}", -(radius - length) * cos))
+                .attr("stroke", "#000000")
+                .attr("stroke-width", 2)
+                .empty();
+        }
+        for (i, point) in ["N", "E", "S", "W"].into_iter().enumerate() {
+            let (sin, cos) = (i as f64 * 90.0).to_radians().sin_cos();
+            let r = radius - 32.0;
+            svg.element("text")
+                .attr("x", format!("{:.1}", r * sin))
+                .attr("y", format!("{:.1}", -r * cos + 8.0))
+                .attr("font-family", FONT_FAMILY)
+                .attr("font-size", 22)
+                .attr("font-weight", "bold")
+                .attr("text-anchor", "middle")
+                .attr("fill", "#000000")
+                .text(point);
+        }
+        svg.element("text")
+            .attr("x", 0)
+            .attr("y", format!("{:.1}", radius + 26.0))
+            .attr("font-family", FONT_FAMILY)
+            .attr("font-size", 20)
+            .attr("text-anchor", "middle")
+            .attr("fill", "#000000")
+            .text(&format!("{} km", self.range_km));
+    }
+}
+
+fn centroid(points: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let (mut x, mut y, mut n) = (0.0, 0.0, 0);
+    for (px, py) in points {
+        x += px;
+        y += py;
+        n += 1;
+    }
+    (n > 0).then(|| (x / n as f64, y / n as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNWAYS: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "name": "RWY 14/32" },
+                "geometry": { "type": "LineString", "coordinates": [[8.536, 47.482, 420], [8.572, 47.453, 420]] }
+            },
+            {
+                "type": "Feature",
+                "properties": { "name": "Tower & Co" },
+                "geometry": { "type": "Point", "coordinates": [8.5510, 47.4600] }
+            },
+            {
+                "type": "Feature",
+                "properties": null,
+                "geometry": { "type": "Polygon", "coordinates": [[[8.4, 47.4], [8.41, 47.4], [8.41, 47.41], [8.4, 47.4]]] }
+            },
+            { "type": "Feature", "properties": {}, "geometry": null }
+        ]
+    }"#;
+
+    const BASEMAP: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "highway": "motorway" },
+                "geometry": { "type": "LineString", "coordinates": [[8.40, 47.39], [8.40001, 47.39001], [8.43, 47.38]] }
+            },
+            {
+                "type": "Feature",
+                "properties": { "natural": "water" },
+                "geometry": { "type": "Polygon", "coordinates": [[[8.35, 47.42], [8.37, 47.42], [8.37, 47.43], [8.35, 47.42]]] }
+            },
+            {
+                "type": "Feature",
+                "properties": { "waterway": "river" },
+                "geometry": { "type": "LineString", "coordinates": [[9.5, 48.0], [9.6, 48.1]] }
+            },
+            {
+                "type": "Feature",
+                "properties": { "building": "yes" },
+                "geometry": { "type": "Polygon", "coordinates": [[[8.40, 47.40], [8.41, 47.40], [8.41, 47.41], [8.40, 47.40]]] }
+            }
+        ]
+    }"#;
+
+    fn inset() -> Inset {
+        Inset {
+            enabled: true,
+            observer: (47.4, 8.4),
+            range_km: 8.0,
+            aircraft: Some(Plot {
+                position: (47.4, 8.4 + 5.0 / (111.195 * 47.4_f64.to_radians().cos())),
+                track: Some(90.0),
+            }),
+            trail: vec![(47.42, 8.45), (47.41, 8.46)],
+            others: vec![(47.45, 8.45)],
+            landmarks: Arc::new(parse_geojson(RUNWAYS).unwrap()),
+            basemap: Arc::new(parse_geojson(BASEMAP).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_parse_geojson() {
+        let features = parse_geojson(RUNWAYS).unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].name.as_deref(), Some("RWY 14/32"));
+        assert_eq!(
+            features[0].shape,
+            Shape::Lines(vec![vec![(47.482, 8.536), (47.453, 8.572)]])
+        );
+        assert_eq!(features[1].shape, Shape::Points(vec![(47.46, 8.551)]));
+        assert_eq!(features[2].name, None);
+
+        let single = r#"{"type": "Feature", "properties": {"name": "Home"}, "geometry": {"type": "Point", "coordinates": [8.4, 47.4]}}"#;
+        assert_eq!(parse_geojson(single).unwrap().len(), 1);
+        assert!(
+            parse_geojson(
+                r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [8.4]}}"#
+            )
+            .is_err()
+        );
+        assert!(parse_geojson(r#"{"type": "Feature", "geometry": {"type": "GeometryCollection", "geometries": []}}"#).is_err());
+    }
+
+    #[test]
+    fn test_render_inset() {
+        let inset = inset();
+        let (x, y) = inset.project(inset.aircraft.as_ref().unwrap().position, 160.0);
+        assert!((x - 100.0).abs() < 0.5 && y.abs() < 0.01, "{} {}", x, y);
+
+        let svg = inset.render(1400.0, 370.0, 160.0);
+        assert!(svg.contains("translate(1400.0, 370.0)"));
+        assert!(svg.contains("translate(100.0, 0.0) rotate(90)"), "{}", svg);
+        assert!(svg.contains(">RWY 14/32</text>"));
+        assert!(svg.contains(">Tower &amp; Co</text>"));
+        assert!(svg.contains(">8 km</text>"));
+        assert!(
+            svg.contains("<polyline points='75.3,-44.5 90.3,-22.2'"),
+            "{}",
+            svg
+        );
+
+        // Water under roads, the nearly repeated point left out, and nothing off the map.
+        let lake = svg.find("fill='#0000FF' fill-rule='evenodd'").unwrap();
+        let motorway = svg
+            .find("<polyline points='0.0,22.2 45.2,44.5' fill='none' stroke='#000000' stroke-width='3'")
+            .unwrap();
+        assert!(lake < motorway, "{}", svg);
+        assert!(!svg.contains("stroke='#0000FF'"), "{}", svg);
+        assert_eq!(svg.matches("fill-rule='evenodd'").count(), 2, "{}", svg);
+        let doc = format!(
+            "<svg xmlns='http://www.w3.org/2000/svg' width='1600' height='1200'>{}</svg>",
+            svg
+        );
+        usvg::Tree::from_str(&doc, &usvg::Options::default()).unwrap();
+    }
+}
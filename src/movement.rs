@@ -0,0 +1,223 @@
+//! Whether an aircraft is arriving at, departing from or flying over the home airport.
+//!
+//! The route decides when there is one, unless the aircraft is low near the airport and
+//! plainly doing the opposite, as adsbdb routes are sometimes those of another flight with the
+//! same callsign. Without a route, low aircraft near the airport are told apart by whether they
+//! are climbing or descending.
+
+use serde::Deserialize;
+
+use crate::geo::haversine_distance;
+use crate::map::LatLon;
+
+/// How far from the home airport aircraft count as near it, in km.
+const NEAR_KM: f64 = 30.0;
+/// The height below which aircraft near the home airport count as low, in meters.
+const LOW_METERS: f64 = 3000.0;
+/// Climb rates beyond this, in m/s, are climbing or descending rather than level.
+const CLIMB_MPS: f64 = 2.5;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HomeAirportConfig {
+    pub icao: String,
+    pub iata: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl HomeAirportConfig {
+    fn is(&self, code: &str) -> bool {
+        code.eq_ignore_ascii_case(&self.icao)
+            || self
+                .iata
+                .as_deref()
+                .is_some_and(|iata| code.eq_ignore_ascii_case(iata))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    Arrival,
+    Departure,
+    Overflight,
+}
+
+impl Movement {
+    /// Name for layouts, e.g. `arrival`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Movement::Arrival => "arrival",
+            Movement::Departure => "departure",
+            Movement::Overflight => "overflight",
+        }
+    }
+
+    /// Badge text.
+    pub fn label(self) -> &'static str {
+        match self {
+            Movement::Arrival => "ARRIVAL",
+            Movement::Departure => "DEPARTURE",
+            Movement::Overflight => "OVERFLIGHT",
+        }
+    }
+
+    /// Badge fill and text colour, from the panel palette.
+    pub fn colors(self) -> (&'static str, &'static str) {
+        match self {
+            Movement::Arrival => ("#0000FF", "#FFFFFF"),
+            Movement::Departure => ("#00FF00", "#000000"),
+            Movement::Overflight => ("#000000", "#FFFFFF"),
+        }
+    }
+}
+
+/// What is known about the aircraft.
+#[derive(Debug, Clone, Default)]
+pub struct Observation<'a> {
+    /// Codes of the route's origin, ICAO or IATA, empty if there is no route.
+    pub origin: Vec<&'a str>,
+    /// Codes of the route's destination.
+    pub destination: Vec<&'a str>,
+    pub position: LatLon,
+    /// In meters.
+    pub altitude: Option<f64>,
+    /// In m/s, negative when descending.
+    pub climb_rate: Option<f64>,
+}
+
+/// Classifies the aircraft, or `None` if there is too little to go on.
+pub fn classify(home: &HomeAirportConfig, aircraft: &Observation) -> Option<Movement> {
+    let (lat, lon) = aircraft.position;
+    let low_and_near = haversine_distance(lat, lon, home.latitude, home.longitude) <= NEAR_KM
+        && aircraft.altitude.is_some_and(|a| a <= LOW_METERS);
+    let trend = aircraft
+        .climb_rate
+        .filter(|_| low_and_near)
+        .and_then(|rate| {
+            if rate >= CLIMB_MPS {
+                Some(Movement::Departure)
+            } else if rate <= -CLIMB_MPS {
+                Some(Movement::Arrival)
+            } else {
+                None
+            }
+        });
+
+    let from_home = aircraft.origin.iter().any(|code| home.is(code));
+    let to_home = aircraft.destination.iter().any(|code| home.is(code));
+    let route = match (from_home, to_home) {
+        // Round trips, e.g. training flights.
+        (true, true) => None,
+        (true, false) => Some(Movement::Departure),
+        (false, true) => Some(Movement::Arrival),
+        (false, false) if !aircraft.destination.is_empty() => Some(Movement::Overflight),
+        (false, false) => None,
+    };
+
+    match (route, trend) {
+        // Low near the airport, what the aircraft is doing beats a route that may be another
+        // flight's.
+        (Some(_), Some(trend)) => Some(trend),
+        (Some(route), None) => Some(route),
+        (None, Some(trend)) => Some(trend),
+        (None, None) if low_and_near => None,
+        (None, None) => Some(Movement::Overflight),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zurich() -> HomeAirportConfig {
+        HomeAirportConfig {
+            icao: "LSZH".to_string(),
+            iata: Some("ZRH".to_string()),
+            latitude: 47.458,
+            longitude: 8.548,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let home = zurich();
+        let near = Observation {
+            position: (47.40, 8.45),
+            altitude: Some(1200.0),
+            ..Default::default()
+        };
+        let classify = |aircraft| classify(&home, &aircraft);
+
+        // By route.
+        assert_eq!(
+            classify(Observation {
+                origin: vec!["LHR", "EGLL"],
+                destination: vec!["ZRH", "LSZH"],
+                ..near.clone()
+            }),
+            Some(Movement::Arrival)
+        );
+        assert_eq!(
+            classify(Observation {
+                origin: vec!["lszh"],
+                destination: vec!["JFK"],
+                climb_rate: Some(1.0),
+                ..near.clone()
+            }),
+            Some(Movement::Departure)
+        );
+        assert_eq!(
+            classify(Observation {
+                origin: vec!["LHR"],
+                destination: vec!["VIE"],
+                position: (47.40, 8.45),
+                altitude: Some(11000.0),
+                climb_rate: Some(0.0),
+            }),
+            Some(Movement::Overflight)
+        );
+        // The route says arrival, but it is climbing out.
+        assert_eq!(
+            classify(Observation {
+                origin: vec!["LHR"],
+                destination: vec!["ZRH"],
+                climb_rate: Some(10.0),
+                ..near.clone()
+            }),
+            Some(Movement::Departure)
+        );
+
+        // By trend.
+        assert_eq!(
+            classify(Observation {
+                climb_rate: Some(-4.0),
+                ..near.clone()
+            }),
+            Some(Movement::Arrival)
+        );
+        assert_eq!(
+            classify(Observation {
+                climb_rate: Some(0.5),
+                ..near.clone()
+            }),
+            None
+        );
+        assert_eq!(
+            classify(Observation {
+                altitude: Some(9000.0),
+                climb_rate: Some(-4.0),
+                ..near.clone()
+            }),
+            Some(Movement::Overflight)
+        );
+        assert_eq!(
+            classify(Observation {
+                position: (46.0, 8.0),
+                climb_rate: Some(-4.0),
+                ..near.clone()
+            }),
+            Some(Movement::Overflight)
+        );
+    }
+}
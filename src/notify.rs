@@ -0,0 +1,252 @@
+//! Events pushed when an aircraft with an alert comes into range, to webhooks and an MQTT topic.
+//!
+//! Each aircraft is announced at most once per local day. Delivery is best effort: failures are
+//! logged and not retried, and MQTT messages are dropped while the broker is unreachable.
+
+use chrono::NaiveDate;
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// How long a webhook may take to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before reconnecting to the broker after an error.
+const MQTT_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Where radar is reached, for the link to the image in events.
+    pub base_url: String,
+    /// URLs events are POSTed to as JSON.
+    pub webhooks: Vec<String>,
+    /// The broker events are published to.
+    pub mqtt: Option<MqttConfig>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            base_url: "http://localhost:3000".to_string(),
+            webhooks: Vec::new(),
+            mqtt: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub topic: String,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            topic: "radar/alerts".to_string(),
+            client_id: "radar".to_string(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+pub struct Notifier {
+    client: reqwest::Client,
+    base_url: String,
+    webhooks: Vec<String>,
+    /// The connection to the broker and the topic.
+    mqtt: Option<(AsyncClient, String)>,
+    /// The local day each aircraft was last announced, by icao24.
+    announced: Mutex<HashMap<String, NaiveDate>>,
+}
+
+impl Notifier {
+    /// Starts connecting to the broker in the background, if one is configured, so it must be
+    /// called within the runtime.
+    pub fn new(config: &NotificationsConfig, client: reqwest::Client) -> Notifier {
+        let mqtt = config.mqtt.as_ref().map(|mqtt| {
+            let mut options = MqttOptions::new(&mqtt.client_id, &mqtt.host, mqtt.port);
+            options.set_keep_alive(Duration::from_secs(60));
+            if let Some(username) = &mqtt.username {
+                options.set_credentials(username, mqtt.password.clone().unwrap_or_default());
+            }
+            let (client, mut eventloop) = AsyncClient::new(options, 10);
+            let broker = format!("{}:{}", mqtt.host, mqtt.port);
+            tokio::spawn(async move {
+                loop {
+                    match eventloop.poll().await {
+                        Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
+                            info!("Connected to MQTT broker {}", broker);
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            error!("MQTT broker {}: {}", broker, e);
+                            tokio::time::sleep(MQTT_RETRY).await;
+                        }
+                    }
+                }
+            });
+            (client, mqtt.topic.clone())
+        });
+        Notifier {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            webhooks: config.webhooks.clone(),
+            mqtt,
+            announced: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether events go anywhere.
+    pub fn is_enabled(&self) -> bool {
+        !self.webhooks.is_empty() || self.mqtt.is_some()
+    }
+
+    /// The link to the current image.
+    pub fn image_url(&self) -> String {
+        format!("{}/image.png", self.base_url)
+    }
+
+    /// Whether `icao24` is yet to be announced on `day`, in which case it now counts as
+    /// announced. Aircraft announced on other days are forgotten.
+    pub fn first_today(&self, icao24: &str, day: NaiveDate) -> bool {
+        let mut announced = self.announced.lock().unwrap();
+        announced.retain(|_, announced_day| *announced_day == day);
+        announced.insert(icao24.to_string(), day).is_none()
+    }
+
+    /// Sends `event` to every webhook and the broker.
+    pub async fn send(&self, event: &Value) {
+        for url in &self.webhooks {
+            let result = self
+                .client
+                .post(url)
+                .json(event)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            if let Err(e) = result {
+                error!("Error posting to webhook {}: {}", url, e);
+            }
+        }
+        if let Some((client, topic)) = &self.mqtt {
+            // Doesn't wait for room in the queue, which is full while the broker is unreachable.
+            if let Err(e) = client.try_publish(topic, QoS::AtLeastOnce, false, event.to_string()) {
+                error!("Error publishing to MQTT topic {}: {}", topic, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, extract::State, routing::post};
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_first_today() {
+        let notifier = Notifier::new(&NotificationsConfig::default(), reqwest::Client::new());
+        assert!(!notifier.is_enabled());
+        assert_eq!(notifier.image_url(), "http://localhost:3000/image.png");
+
+        let day = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+        assert!(notifier.first_today("4b1805", day));
+        assert!(!notifier.first_today("4b1805", day));
+        assert!(notifier.first_today("ae1234", day));
+        let next_day = day.succ_opt().unwrap();
+        assert!(notifier.first_today("4b1805", next_day));
+        assert_eq!(notifier.announced.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(tx): State<mpsc::Sender<Value>>, Json(event): Json<Value>| async move {
+                        tx.send(event).await.unwrap();
+                    },
+                ),
+            )
+            .with_state(tx);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let notifier = Notifier::new(
+            &NotificationsConfig {
+                base_url: "http://radar.local/".to_string(),
+                webhooks: vec![format!("http://{}/hook", addr)],
+                mqtt: None,
+            },
+            reqwest::Client::new(),
+        );
+        assert!(notifier.is_enabled());
+        assert_eq!(notifier.image_url(), "http://radar.local/image.png");
+        let event = json!({ "icao24": "4b1805", "alert": "emergency" });
+        notifier.send(&event).await;
+        assert_eq!(rx.recv().await.unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn test_mqtt() {
+        // Just enough of a broker to accept the connection and receive the publish.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            assert_eq!(buf[0], 0x10, "CONNECT");
+            received.extend_from_slice(&buf[..n]);
+            socket.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+            while !String::from_utf8_lossy(&received).contains("\"4b1805\"}") {
+                let n = socket.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed");
+                received.extend_from_slice(&buf[..n]);
+            }
+            String::from_utf8_lossy(&received).into_owned()
+        });
+
+        let notifier = Notifier::new(
+            &NotificationsConfig {
+                mqtt: Some(MqttConfig {
+                    host: "127.0.0.1".to_string(),
+                    port,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            reqwest::Client::new(),
+        );
+        notifier
+            .send(&json!({ "icao24": "4b1805", "alert": "emergency" }))
+            .await;
+        let received = tokio::time::timeout(Duration::from_secs(10), broker)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(received.contains("radar/alerts"));
+        assert!(received.contains(r#"{"alert":"emergency","icao24":"4b1805"}"#));
+    }
+}
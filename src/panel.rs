@@ -0,0 +1,624 @@
+//! E-paper panel descriptions and the packed framebuffer format sent to them.
+
+use serde::Deserialize;
+use tiny_skia::Pixmap;
+
+/// A colour the panel can show, and the value the controller expects for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteEntry {
+    /// The colour dithering aims for.
+    pub rgb: [u8; 3],
+    pub index: u8,
+    /// Roughly what the pigment looks like on the panel, for previews.
+    pub display: [u8; 3],
+}
+
+const fn entry(rgb: [u8; 3], index: u8, display: [u8; 3]) -> PaletteEntry {
+    PaletteEntry {
+        rgb,
+        index,
+        display,
+    }
+}
+
+/// Shown in previews for values that aren't in the palette.
+const INVALID_DISPLAY_COLOR: [u8; 3] = [255, 0, 255];
+
+/// Clockwise rotation applied to the rendered image to match the panel's scan direction.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "u16")]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::Deg0),
+            90 => Ok(Rotation::Deg90),
+            180 => Ok(Rotation::Deg180),
+            270 => Ok(Rotation::Deg270),
+            _ => Err(format!(
+                "invalid rotation {}, expected 0, 90, 180 or 270",
+                degrees
+            )),
+        }
+    }
+}
+
+impl Rotation {
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 90,
+            Rotation::Deg180 => 180,
+            Rotation::Deg270 => 270,
+        }
+    }
+}
+
+/// Flip of the rotated image, for panels mounted behind glass or scanned in reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mirror {
+    None,
+    /// Swap left and right.
+    Horizontal,
+    /// Swap top and bottom.
+    Vertical,
+}
+
+impl Mirror {
+    /// Name as in the config file, e.g. `horizontal`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mirror::None => "none",
+            Mirror::Horizontal => "horizontal",
+            Mirror::Vertical => "vertical",
+        }
+    }
+}
+
+/// How the framebuffer is divided between the panel's driver chips.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StripLayout {
+    /// One buffer for the whole panel.
+    #[serde(rename = "none")]
+    Single,
+    /// The left and right halves of the native frame, one after the other.
+    VerticalHalves,
+    /// The top and bottom halves of the native frame, one after the other.
+    HorizontalHalves,
+}
+
+impl StripLayout {
+    /// Name as in the config file, e.g. `vertical-halves`.
+    pub fn name(self) -> &'static str {
+        match self {
+            StripLayout::Single => "none",
+            StripLayout::VerticalHalves => "vertical-halves",
+            StripLayout::HorizontalHalves => "horizontal-halves",
+        }
+    }
+}
+
+/// Overrides for the orientation and layout of a built-in profile, from the config file or the
+/// request.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutOverrides {
+    pub rotation: Option<Rotation>,
+    pub mirror: Option<Mirror>,
+    pub strips: Option<StripLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanelProfile {
+    pub name: &'static str,
+    /// Identifies the profile in framed transfers.
+    pub id: u8,
+    pub description: &'static str,
+    /// Resolution in the orientation the controller scans the panel.
+    pub width: usize,
+    pub height: usize,
+    pub rotation: Rotation,
+    pub mirror: Mirror,
+    pub strips: StripLayout,
+    pub bits_per_pixel: u8,
+    pub palette: &'static [PaletteEntry],
+}
+
+pub const SPECTRA6_PALETTE: &[PaletteEntry] = &[
+    entry([0, 0, 0], 0, [25, 30, 33]),          // Black
+    entry([255, 255, 255], 1, [232, 232, 232]), // White
+    entry([255, 255, 0], 2, [239, 222, 68]),    // Yellow
+    entry([255, 0, 0], 3, [178, 19, 24]),       // Red
+    entry([0, 0, 255], 5, [33, 87, 186]),       // Blue
+    entry([0, 255, 0], 6, [18, 95, 32]),        // Green
+];
+
+const ACEP7_PALETTE: &[PaletteEntry] = &[
+    entry([0, 0, 0], 0, [57, 48, 57]),          // Black
+    entry([255, 255, 255], 1, [255, 255, 255]), // White
+    entry([0, 255, 0], 2, [58, 91, 70]),        // Green
+    entry([0, 0, 255], 3, [61, 59, 94]),        // Blue
+    entry([255, 0, 0], 4, [156, 72, 75]),       // Red
+    entry([255, 255, 0], 5, [208, 190, 71]),    // Yellow
+    entry([255, 128, 0], 6, [177, 106, 73]),    // Orange
+];
+
+const BWR_PALETTE: &[PaletteEntry] = &[
+    entry([0, 0, 0], 0, [30, 30, 30]),          // Black
+    entry([255, 255, 255], 1, [235, 235, 228]), // White
+    entry([255, 0, 0], 2, [180, 30, 30]),       // Red
+];
+
+const MONO_PALETTE: &[PaletteEntry] = &[
+    entry([0, 0, 0], 0, [30, 30, 30]),          // Black
+    entry([255, 255, 255], 1, [235, 235, 228]), // White
+];
+
+pub const DEFAULT_PANEL: &str = "spectra6-13in3";
+
+pub const PROFILES: &[PanelProfile] = &[
+    PanelProfile {
+        name: "spectra6-13in3",
+        id: 1,
+        description: "13.3\" Spectra 6, 1200x1600 portrait driven as two 600px strips",
+        width: 1200,
+        height: 1600,
+        rotation: Rotation::Deg90,
+        mirror: Mirror::None,
+        strips: StripLayout::VerticalHalves,
+        bits_per_pixel: 4,
+        palette: SPECTRA6_PALETTE,
+    },
+    PanelProfile {
+        name: "acep7-7in3",
+        id: 2,
+        description: "7.3\" ACeP 7-colour, 800x480",
+        width: 800,
+        height: 480,
+        rotation: Rotation::Deg0,
+        mirror: Mirror::None,
+        strips: StripLayout::Single,
+        bits_per_pixel: 4,
+        palette: ACEP7_PALETTE,
+    },
+    PanelProfile {
+        name: "bwr-7in5",
+        id: 3,
+        description: "7.5\" black/white/red, 800x480",
+        width: 800,
+        height: 480,
+        rotation: Rotation::Deg0,
+        mirror: Mirror::None,
+        strips: StripLayout::Single,
+        bits_per_pixel: 2,
+        palette: BWR_PALETTE,
+    },
+    PanelProfile {
+        name: "mono-7in5",
+        id: 4,
+        description: "7.5\" black/white Waveshare, 800x480",
+        width: 800,
+        height: 480,
+        rotation: Rotation::Deg0,
+        mirror: Mirror::None,
+        strips: StripLayout::Single,
+        bits_per_pixel: 1,
+        palette: MONO_PALETTE,
+    },
+];
+
+pub fn find_profile(name: &str) -> Option<&'static PanelProfile> {
+    PROFILES.iter().find(|p| p.name == name)
+}
+
+pub fn find_profile_by_id(id: u8) -> Option<&'static PanelProfile> {
+    PROFILES.iter().find(|p| p.id == id)
+}
+
+pub fn profile_names() -> Vec<&'static str> {
+    PROFILES.iter().map(|p| p.name).collect()
+}
+
+impl PanelProfile {
+    pub fn with_overrides(&self, overrides: &LayoutOverrides) -> PanelProfile {
+        PanelProfile {
+            rotation: overrides.rotation.unwrap_or(self.rotation),
+            mirror: overrides.mirror.unwrap_or(self.mirror),
+            strips: overrides.strips.unwrap_or(self.strips),
+            ..self.clone()
+        }
+    }
+
+    /// The size the image has to be rendered at, before it is rotated onto the panel.
+    pub fn canvas_size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (self.width, self.height),
+            Rotation::Deg90 | Rotation::Deg270 => (self.height, self.width),
+        }
+    }
+
+    /// The controller value for `rgb`, using the closest palette colour if there is no exact
+    /// match.
+    pub fn color_index(&self, rgb: [u8; 3]) -> u8 {
+        self.palette
+            .iter()
+            .min_by_key(|e| {
+                e.rgb
+                    .iter()
+                    .zip(rgb)
+                    .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+                    .sum::<i32>()
+            })
+            .map(|e| e.index)
+            .unwrap_or(0)
+    }
+
+    /// Position in the canvas that ends up at native panel position (`x`, `y`).
+    fn canvas_position(&self, x: usize, y: usize) -> (usize, usize) {
+        let (w, h) = (self.width, self.height);
+        let (x, y) = match self.mirror {
+            Mirror::None => (x, y),
+            Mirror::Horizontal => (w - 1 - x, y),
+            Mirror::Vertical => (x, h - 1 - y),
+        };
+        match self.rotation {
+            Rotation::Deg0 => (x, y),
+            // Rotated clockwise, the left column of the canvas becomes the top row of the panel:
+            // x_new = (canvas_h - 1) - y_old, y_new = x_old
+            Rotation::Deg90 => (y, w - 1 - x),
+            Rotation::Deg180 => (w - 1 - x, h - 1 - y),
+            Rotation::Deg270 => (h - 1 - y, x),
+        }
+    }
+
+    fn strip_rects(&self) -> Vec<(usize, usize, usize, usize)> {
+        match self.strips {
+            StripLayout::Single => vec![(0, 0, self.width, self.height)],
+            StripLayout::VerticalHalves => {
+                let half = self.width / 2;
+                vec![
+                    (0, 0, half, self.height),
+                    (half, 0, self.width - half, self.height),
+                ]
+            }
+            StripLayout::HorizontalHalves => {
+                let half = self.height / 2;
+                vec![
+                    (0, 0, self.width, half),
+                    (0, half, self.width, self.height - half),
+                ]
+            }
+        }
+    }
+
+    /// Bytes per row of a strip `width` pixels wide. Rows are padded to whole bytes.
+    pub fn row_bytes(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel as usize).div_ceil(8)
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        self.strip_rects()
+            .iter()
+            .map(|&(_, _, w, h)| self.row_bytes(w) * h)
+            .sum()
+    }
+}
+
+/// Packs a canvas-sized pixmap into the panel's framebuffer: rotated and mirrored to the native
+/// orientation, split into strips, and packed most significant bits first.
+pub fn pixmap_to_epd_bin(pixmap: &Pixmap, profile: &PanelProfile) -> Vec<u8> {
+    indices_to_epd_bin(&native_indices(pixmap, profile), profile)
+}
+
+/// Packs the controller values from [`native_indices`] into the panel's framebuffer.
+pub fn indices_to_epd_bin(indices: &[u8], profile: &PanelProfile) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(profile.buffer_len());
+    for rect in profile.strip_rects() {
+        pack_rect(indices, profile, rect, &mut buffer);
+    }
+    buffer
+}
+
+/// The controller value of every panel pixel, row by row in native orientation.
+pub fn native_indices(pixmap: &Pixmap, profile: &PanelProfile) -> Vec<u8> {
+    let src_w = pixmap.width() as usize;
+    let src_h = pixmap.height() as usize;
+    let pixels = pixmap.pixels();
+
+    let mut indices = vec![0u8; profile.width * profile.height];
+    for y in 0..profile.height {
+        for x in 0..profile.width {
+            let (x_old, y_old) = profile.canvas_position(x, y);
+            if x_old >= src_w || y_old >= src_h {
+                continue;
+            }
+
+            let p = pixels[y_old * src_w + x_old].demultiply();
+            indices[y * profile.width + x] = profile.color_index([p.red(), p.green(), p.blue()]);
+        }
+    }
+    indices
+}
+
+/// Appends the pixels of the native rectangle (`x`, `y`, `w`, `h`) to `out`, row by row, packed
+/// most significant bits first with each row padded to whole bytes.
+pub fn pack_rect(
+    indices: &[u8],
+    profile: &PanelProfile,
+    (x, y, w, h): (usize, usize, usize, usize),
+    out: &mut Vec<u8>,
+) {
+    let bpp = profile.bits_per_pixel as usize;
+    let row_bytes = profile.row_bytes(w);
+    for row in y..y + h {
+        let start = out.len();
+        out.resize(start + row_bytes, 0);
+        let line = &indices[row * profile.width + x..row * profile.width + x + w];
+        for (i, &color) in line.iter().enumerate() {
+            let bit = i * bpp;
+            out[start + bit / 8] |= color << (8 - bpp - bit % 8);
+        }
+    }
+}
+
+/// Unpacks a framebuffer into the controller's colour values, un-split and rotated back into
+/// canvas order.
+pub fn decode_indices(bin: &[u8], profile: &PanelProfile) -> Result<Vec<u8>, String> {
+    if bin.len() != profile.buffer_len() {
+        return Err(format!(
+            "expected {} bytes for panel {}, got {}",
+            profile.buffer_len(),
+            profile.name,
+            bin.len()
+        ));
+    }
+
+    let (cw, ch) = profile.canvas_size();
+    let bpp = profile.bits_per_pixel as usize;
+    let mask = ((1u16 << bpp) - 1) as u8;
+
+    let mut indices = vec![0u8; cw * ch];
+    let mut offset = 0;
+
+    for (sx, sy, sw, sh) in profile.strip_rects() {
+        let row_bytes = profile.row_bytes(sw);
+        for y in 0..sh {
+            for x in 0..sw {
+                let bit = x * bpp;
+                let byte = bin[offset + y * row_bytes + bit / 8];
+                let (cx, cy) = profile.canvas_position(sx + x, sy + y);
+                indices[cy * cw + cx] = (byte >> (8 - bpp - bit % 8)) & mask;
+            }
+        }
+        offset += row_bytes * sh;
+    }
+
+    Ok(indices)
+}
+
+/// Decodes a framebuffer into an image of roughly what the panel will show.
+pub fn epd_bin_to_pixmap(bin: &[u8], profile: &PanelProfile) -> Result<Pixmap, String> {
+    let indices = decode_indices(bin, profile)?;
+    let (cw, ch) = profile.canvas_size();
+
+    let mut pixmap = Pixmap::new(cw as u32, ch as u32).ok_or("empty panel")?;
+    for (p, index) in pixmap.pixels_mut().iter_mut().zip(indices) {
+        let [r, g, b] = profile
+            .palette
+            .iter()
+            .find(|e| e.index == index)
+            .map(|e| e.display)
+            .unwrap_or(INVALID_DISPLAY_COLOR);
+        *p = tiny_skia::ColorU8::from_rgba(r, g, b, 255).premultiply();
+    }
+
+    Ok(pixmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_pixmap(width: u32, height: u32, rgb: [u8; 3]) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255));
+        pixmap
+    }
+
+    #[test]
+    fn test_spectra6_layout() {
+        let profile = find_profile("spectra6-13in3").unwrap();
+        assert_eq!(profile.canvas_size(), (1600, 1200));
+
+        // Top-left canvas pixel is red, everything else white.
+        let mut pixmap = solid_pixmap(1600, 1200, [255, 255, 255]);
+        pixmap.pixels_mut()[0] = tiny_skia::ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+
+        let bin = pixmap_to_epd_bin(&pixmap, profile);
+        assert_eq!(bin.len(), 960_000);
+
+        // Rotated clockwise, the top-left canvas corner is the top-right native corner, which is
+        // the last pixel of the first row of the second strip.
+        let half = bin.len() / 2;
+        assert_eq!(bin[half + 299], 0x13);
+        assert_eq!(bin[half + 298], 0x11);
+        assert_eq!(bin[0], 0x11);
+    }
+
+    #[test]
+    fn test_bit_depths() {
+        let mono = find_profile("mono-7in5").unwrap();
+        let bin = pixmap_to_epd_bin(&solid_pixmap(800, 480, [255, 255, 255]), mono);
+        assert_eq!(bin.len(), 800 * 480 / 8);
+        assert!(bin.iter().all(|&b| b == 0xff));
+
+        let bwr = find_profile("bwr-7in5").unwrap();
+        let bin = pixmap_to_epd_bin(&solid_pixmap(800, 480, [255, 0, 0]), bwr);
+        assert_eq!(bin.len(), 800 * 480 / 4);
+        assert!(bin.iter().all(|&b| b == 0b1010_1010));
+    }
+
+    /// Where canvas position (`x`, `y`) lands on the panel, computed in the forward direction
+    /// so that it checks `canvas_position` rather than reusing it.
+    fn native_position(profile: &PanelProfile, x: usize, y: usize) -> (usize, usize) {
+        let (cw, ch) = profile.canvas_size();
+        let (x, y) = match profile.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (ch - 1 - y, x),
+            Rotation::Deg180 => (cw - 1 - x, ch - 1 - y),
+            Rotation::Deg270 => (y, cw - 1 - x),
+        };
+        match profile.mirror {
+            Mirror::None => (x, y),
+            Mirror::Horizontal => (profile.width - 1 - x, y),
+            Mirror::Vertical => (x, profile.height - 1 - y),
+        }
+    }
+
+    /// Decodes a framebuffer back into colour indices in canvas order.
+    fn decode_to_canvas(bin: &[u8], profile: &PanelProfile) -> Vec<u8> {
+        let bpp = profile.bits_per_pixel as usize;
+        let mask = (1u8 << bpp) - 1;
+        let (cw, ch) = profile.canvas_size();
+        let mut out = Vec::with_capacity(cw * ch);
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (x, y) = native_position(profile, cx, cy);
+                let mut offset = 0;
+                for (sx, sy, sw, sh) in profile.strip_rects() {
+                    let row_bytes = profile.row_bytes(sw);
+                    if (sx..sx + sw).contains(&x) && (sy..sy + sh).contains(&y) {
+                        let bit = (x - sx) * bpp;
+                        let byte = bin[offset + (y - sy) * row_bytes + bit / 8];
+                        out.push((byte >> (8 - bpp - bit % 8)) & mask);
+                        break;
+                    }
+                    offset += row_bytes * sh;
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_round_trip_all_layouts() {
+        let rotations = [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ];
+        let mirrors = [Mirror::None, Mirror::Horizontal, Mirror::Vertical];
+        let strips = [
+            StripLayout::Single,
+            StripLayout::VerticalHalves,
+            StripLayout::HorizontalHalves,
+        ];
+
+        for base in PROFILES {
+            // Small, odd-sized panels so that padding and uneven halves are exercised.
+            let base = PanelProfile {
+                width: 10,
+                height: 7,
+                ..base.clone()
+            };
+            for rotation in rotations {
+                for mirror in mirrors {
+                    for strips in strips {
+                        let profile = base.with_overrides(&LayoutOverrides {
+                            rotation: Some(rotation),
+                            mirror: Some(mirror),
+                            strips: Some(strips),
+                        });
+
+                        let (cw, ch) = profile.canvas_size();
+                        let mut pixmap = Pixmap::new(cw as u32, ch as u32).unwrap();
+                        let mut expected = Vec::new();
+                        for (i, p) in pixmap.pixels_mut().iter_mut().enumerate() {
+                            let e = profile.palette[(i * 7 + i / cw) % profile.palette.len()];
+                            *p = tiny_skia::ColorU8::from_rgba(e.rgb[0], e.rgb[1], e.rgb[2], 255)
+                                .premultiply();
+                            expected.push(e.index);
+                        }
+
+                        let bin = pixmap_to_epd_bin(&pixmap, &profile);
+                        assert_eq!(bin.len(), profile.buffer_len());
+                        assert_eq!(decode_indices(&bin, &profile).unwrap(), expected);
+                        assert_eq!(
+                            decode_to_canvas(&bin, &profile),
+                            expected,
+                            "{} {:?} {:?} {:?}",
+                            profile.name,
+                            rotation,
+                            mirror,
+                            strips
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_preview_uses_display_colors() {
+        let profile = find_profile(DEFAULT_PANEL).unwrap();
+        let (cw, ch) = profile.canvas_size();
+        let mut pixmap = Pixmap::new(cw as u32, ch as u32).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(255, 255, 0, 255));
+        pixmap.pixels_mut()[cw + 1] = tiny_skia::ColorU8::from_rgba(0, 0, 255, 255).premultiply();
+
+        let bin = pixmap_to_epd_bin(&pixmap, profile);
+        let preview = epd_bin_to_pixmap(&bin, profile).unwrap();
+        assert_eq!(preview.width() as usize, cw);
+        assert_eq!(preview.height() as usize, ch);
+
+        let rgb = |p: tiny_skia::PremultipliedColorU8| [p.red(), p.green(), p.blue()];
+        assert_eq!(rgb(preview.pixels()[0]), [239, 222, 68]);
+        assert_eq!(rgb(preview.pixels()[cw + 1]), [33, 87, 186]);
+
+        // Index 4 is unused on the Spectra 6.
+        let mut bin = bin;
+        bin[0] = 0x44;
+        let preview = epd_bin_to_pixmap(&bin, profile).unwrap();
+        assert!(
+            preview
+                .pixels()
+                .iter()
+                .any(|&p| rgb(p) == INVALID_DISPLAY_COLOR)
+        );
+
+        assert!(epd_bin_to_pixmap(&bin[1..], profile).is_err());
+    }
+
+    #[test]
+    fn test_parse_layout_overrides() {
+        let overrides: LayoutOverrides =
+            toml::from_str("rotation = 270\nmirror = 'horizontal'\nstrips = 'horizontal-halves'")
+                .unwrap();
+        assert_eq!(overrides.rotation, Some(Rotation::Deg270));
+        assert_eq!(overrides.mirror, Some(Mirror::Horizontal));
+        assert_eq!(overrides.strips, Some(StripLayout::HorizontalHalves));
+
+        let overrides: LayoutOverrides = toml::from_str("strips = 'none'").unwrap();
+        assert_eq!(overrides.strips, Some(StripLayout::Single));
+        assert!(toml::from_str::<LayoutOverrides>("rotation = 45").is_err());
+    }
+
+    #[test]
+    fn test_color_index_snaps_to_palette() {
+        let acep = find_profile("acep7-7in3").unwrap();
+        assert_eq!(acep.color_index([255, 128, 0]), 6);
+        assert_eq!(acep.color_index([250, 120, 10]), 6);
+        assert_eq!(acep.color_index([10, 10, 10]), 0);
+    }
+}
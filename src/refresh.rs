@@ -0,0 +1,130 @@
+//! How long the device should sleep before polling again.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefreshConfig {
+    /// While an aircraft is being shown.
+    pub active_seconds: u64,
+    /// Lower bound for waiting on an approaching aircraft.
+    pub min_seconds: u64,
+    /// When nothing is overhead or on its way.
+    pub idle_seconds: u64,
+    /// When nothing is overhead at night.
+    pub night_seconds: u64,
+    /// Local hour the night starts, inclusive.
+    pub night_start_hour: u32,
+    /// Local hour the night ends, exclusive.
+    pub night_end_hour: u32,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig {
+            active_seconds: 60,
+            min_seconds: 30,
+            idle_seconds: 300,
+            night_seconds: 1800,
+            night_start_hour: 0,
+            night_end_hour: 6,
+        }
+    }
+}
+
+impl RefreshConfig {
+    pub fn is_night(&self, hour: u32) -> bool {
+        if self.night_start_hour <= self.night_end_hour {
+            (self.night_start_hour..self.night_end_hour).contains(&hour)
+        } else {
+            hour >= self.night_start_hour || hour < self.night_end_hour
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RefreshHint {
+    pub seconds: u64,
+    pub reason: &'static str,
+}
+
+/// Picks the next poll delay from what is in the sky: `overhead` if an aircraft is in range,
+/// otherwise `next_arrival`, the seconds until the soonest approaching aircraft gets in range.
+pub fn next_refresh(
+    overhead: bool,
+    next_arrival: Option<f64>,
+    local_hour: u32,
+    config: &RefreshConfig,
+) -> RefreshHint {
+    if overhead {
+        return RefreshHint {
+            seconds: config.active_seconds,
+            reason: "aircraft overhead",
+        };
+    }
+
+    let quiet = if config.is_night(local_hour) {
+        RefreshHint {
+            seconds: config.night_seconds,
+            reason: "night",
+        }
+    } else {
+        RefreshHint {
+            seconds: config.idle_seconds,
+            reason: "sky empty",
+        }
+    };
+
+    match next_arrival {
+        Some(t) if (t as u64) < quiet.seconds => RefreshHint {
+            seconds: (t as u64).max(config.min_seconds),
+            reason: "aircraft approaching",
+        },
+        _ => quiet,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_refresh() {
+        let config = RefreshConfig::default();
+
+        let hint = next_refresh(true, None, 3, &config);
+        assert_eq!((hint.seconds, hint.reason), (60, "aircraft overhead"));
+
+        let hint = next_refresh(false, None, 14, &config);
+        assert_eq!((hint.seconds, hint.reason), (300, "sky empty"));
+
+        let hint = next_refresh(false, Some(95.4), 14, &config);
+        assert_eq!((hint.seconds, hint.reason), (95, "aircraft approaching"));
+
+        let hint = next_refresh(false, Some(5.0), 14, &config);
+        assert_eq!(hint.seconds, 30);
+
+        // Too far out to matter before the next idle poll anyway.
+        let hint = next_refresh(false, Some(900.0), 14, &config);
+        assert_eq!(hint.reason, "sky empty");
+
+        let hint = next_refresh(false, None, 2, &config);
+        assert_eq!((hint.seconds, hint.reason), (1800, "night"));
+        let hint = next_refresh(false, Some(900.0), 2, &config);
+        assert_eq!((hint.seconds, hint.reason), (900, "aircraft approaching"));
+    }
+
+    #[test]
+    fn test_night_wraps_midnight() {
+        let config = RefreshConfig {
+            night_start_hour: 23,
+            night_end_hour: 6,
+            ..Default::default()
+        };
+        assert!(config.is_night(23));
+        assert!(config.is_night(0));
+        assert!(config.is_night(5));
+        assert!(!config.is_night(6));
+        assert!(!config.is_night(22));
+    }
+}
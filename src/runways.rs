@@ -0,0 +1,279 @@
+//! Which runways are in use, inferred from the traffic around the airport.
+//!
+//! Each time traffic is fetched, aircraft low over a runway's threshold elevation are matched
+//! against the configured runways: those descending on the extended centreline before a
+//! threshold, heading along the runway, are landing on it, and those climbing away beyond a
+//! threshold along its heading are departing from it. Only the latest sighting of each aircraft
+//! in the last `WINDOW_MINUTES` counts, so that one slow approach doesn't outvote the rest.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::geo::local_offset_km;
+use crate::traffic::Aircraft;
+
+/// How far back sightings count.
+pub const WINDOW_MINUTES: i64 = 30;
+/// Height above the threshold up to which aircraft are matched, in meters.
+const MAX_HEIGHT_METERS: f64 = 1500.0;
+/// Climb rate beyond which aircraft are climbing or descending, in m/s.
+const CLIMB_MPS: f64 = 1.0;
+/// How far out on the approach aircraft are matched, in km before the threshold.
+const MAX_APPROACH_KM: f64 = 20.0;
+/// How far out departures are matched, in km beyond the threshold.
+const MAX_DEPARTURE_KM: f64 = 15.0;
+/// How far off the extended centreline aircraft may be, in km.
+const MAX_OFFSET_KM: f64 = 1.5;
+/// How far the track may differ from the runway heading, in degrees.
+const MAX_HEADING_DIFFERENCE: f64 = 15.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunwayConfig {
+    /// Designator, e.g. `14`.
+    pub name: String,
+    /// Position of the threshold, where landings touch down and departures start their roll.
+    pub latitude: f64,
+    pub longitude: f64,
+    /// True heading of aircraft using the runway, in degrees.
+    pub heading: f64,
+    /// Threshold elevation in meters.
+    #[serde(default)]
+    pub elevation: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Operation {
+    Landing,
+    Departure,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub runway: String,
+    /// Aircraft seen using it.
+    pub aircraft: usize,
+    /// `aircraft` out of all aircraft seen landing or departing, plus one, so that a single
+    /// aircraft makes 0.5 and more aircraft agreeing make it approach 1.
+    pub confidence: f64,
+}
+
+/// Runways by how many aircraft have been seen using them, most first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunwaysInUse {
+    pub landing: Vec<Candidate>,
+    pub departure: Vec<Candidate>,
+    pub window_minutes: i64,
+}
+
+impl RunwaysInUse {
+    /// The most used runways, e.g. `Landing 14 · Departing 16`, or `None` if no aircraft has
+    /// been seen.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [("Landing", &self.landing), ("Departing", &self.departure)]
+            .into_iter()
+            .filter_map(|(label, candidates)| {
+                Some(format!("{} {}", label, candidates.first()?.runway))
+            })
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+pub struct RunwayWatch {
+    runways: Vec<RunwayConfig>,
+    /// Runway and time of the latest sighting, by icao24 and operation.
+    sightings: HashMap<(String, Operation), (usize, DateTime<Utc>)>,
+}
+
+impl RunwayWatch {
+    pub fn new(runways: Vec<RunwayConfig>) -> RunwayWatch {
+        RunwayWatch {
+            runways,
+            sightings: HashMap::new(),
+        }
+    }
+
+    /// Records the aircraft polled at `now` that are landing or departing, and forgets sightings
+    /// older than the window.
+    pub fn observe<'a>(
+        &mut self,
+        traffic: impl IntoIterator<Item = &'a Aircraft>,
+        now: DateTime<Utc>,
+    ) {
+        for aircraft in traffic {
+            if let Some((runway, operation)) = self.match_runway(aircraft) {
+                self.sightings
+                    .insert((aircraft.icao24.clone(), operation), (runway, now));
+            }
+        }
+        let cutoff = now - TimeDelta::minutes(WINDOW_MINUTES);
+        self.sightings.retain(|_, (_, time)| *time > cutoff);
+    }
+
+    pub fn in_use(&self) -> RunwaysInUse {
+        RunwaysInUse {
+            landing: self.candidates(Operation::Landing),
+            departure: self.candidates(Operation::Departure),
+            window_minutes: WINDOW_MINUTES,
+        }
+    }
+
+    fn candidates(&self, operation: Operation) -> Vec<Candidate> {
+        let mut counts = vec![0; self.runways.len()];
+        for ((_, op), (runway, _)) in &self.sightings {
+            if *op == operation {
+                counts[*runway] += 1;
+            }
+        }
+        let total: usize = counts.iter().sum();
+        let mut candidates: Vec<Candidate> = counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(runway, count)| Candidate {
+                runway: self.runways[runway].name.clone(),
+                aircraft: count,
+                confidence: count as f64 / (total + 1) as f64,
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.aircraft.cmp(&a.aircraft).then(a.runway.cmp(&b.runway)));
+        candidates
+    }
+
+    /// The runway `aircraft` is landing on or departing from, the one whose centreline it is
+    /// closest to if several match.
+    fn match_runway(&self, aircraft: &Aircraft) -> Option<(usize, Operation)> {
+        if aircraft.on_ground {
+            return None;
+        }
+        let (track, climb_rate) = (aircraft.true_track?, aircraft.vertical_rate?);
+        let altitude = aircraft.baro_altitude.or(aircraft.geo_altitude)?;
+        let operation = if climb_rate >= CLIMB_MPS {
+            Operation::Departure
+        } else if climb_rate <= -CLIMB_MPS {
+            Operation::Landing
+        } else {
+            return None;
+        };
+
+        self.runways
+            .iter()
+            .enumerate()
+            .filter_map(|(i, runway)| {
+                let height = altitude - runway.elevation;
+                let heading_difference = (track - runway.heading + 540.0).rem_euclid(360.0) - 180.0;
+                if !(0.0..=MAX_HEIGHT_METERS).contains(&height)
+                    || heading_difference.abs() > MAX_HEADING_DIFFERENCE
+                {
+                    return None;
+                }
+                let (east, north) = local_offset_km(
+                    runway.latitude,
+                    runway.longitude,
+                    aircraft.latitude,
+                    aircraft.longitude,
+                );
+                let (sin, cos) = runway.heading.to_radians().sin_cos();
+                // Distance beyond the threshold along the runway, and off the centreline.
+                let along = east * sin + north * cos;
+                let offset = (east * cos - north * sin).abs();
+                let ahead = match operation {
+                    Operation::Landing => (-MAX_APPROACH_KM..=0.0).contains(&along),
+                    Operation::Departure => (0.0..=MAX_DEPARTURE_KM).contains(&along),
+                };
+                (ahead && offset <= MAX_OFFSET_KM).then_some((i, offset))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| (i, operation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Zurich's 14/32 and 16/34, roughly.
+    fn runways() -> Vec<RunwayConfig> {
+        let runway = |name: &str, latitude, longitude, heading| RunwayConfig {
+            name: name.to_string(),
+            latitude,
+            longitude,
+            heading,
+            elevation: 420.0,
+        };
+        vec![
+            runway("14", 47.4823, 8.5368, 137.0),
+            runway("32", 47.4606, 8.5666, 317.0),
+            runway("16", 47.4745, 8.5353, 156.0),
+            runway("34", 47.4441, 8.5553, 336.0),
+        ]
+    }
+
+    /// An aircraft `km` along `heading` from (`lat`, `lon`), flying along it.
+    fn aircraft(icao24: &str, from: (f64, f64), heading: f64, km: f64, climb: f64) -> Aircraft {
+        let (sin, cos) = heading.to_radians().sin_cos();
+        let latitude = from.0 + km * cos / 111.2;
+        let longitude = from.1 + km * sin / (111.2 * from.0.to_radians().cos());
+        Aircraft {
+            latitude,
+            longitude,
+            baro_altitude: Some(420.0 + km.abs() * 100.0),
+            velocity: Some(80.0),
+            true_track: Some(heading),
+            vertical_rate: Some(climb),
+            ..Aircraft::test(icao24, 0.0)
+        }
+    }
+
+    #[test]
+    fn test_in_use() {
+        let start = Utc::now();
+        let mut watch = RunwayWatch::new(runways());
+        let rwy14 = (47.4823, 8.5368);
+        let rwy16 = (47.4745, 8.5353);
+        let traffic = [
+            // On final for 14, 8 and 4 km out.
+            aircraft("a1", rwy14, 137.0, -8.0, -3.5),
+            aircraft("a2", rwy14, 137.0, -4.0, -3.5),
+            // Climbing out of 16, 3 km beyond the threshold.
+            aircraft("d1", rwy16, 156.0, 3.0, 10.0),
+            // Level, climbing away from the wrong end, and too high.
+            aircraft("x1", rwy14, 137.0, -6.0, 0.0),
+            aircraft("x2", rwy14, 137.0, -3.0, 10.0),
+            Aircraft {
+                baro_altitude: Some(5000.0),
+                ..aircraft("x3", rwy14, 137.0, -8.0, -3.5)
+            },
+        ];
+        watch.observe(&traffic, start);
+        // The same aircraft again doesn't count twice.
+        watch.observe(&traffic[..1], start + TimeDelta::minutes(1));
+
+        let in_use = watch.in_use();
+        assert_eq!(
+            in_use.landing,
+            vec![Candidate {
+                runway: "14".to_string(),
+                aircraft: 2,
+                confidence: 2.0 / 3.0,
+            }]
+        );
+        assert_eq!(in_use.departure.len(), 1);
+        assert_eq!(in_use.departure[0].runway, "16");
+        assert_eq!(
+            in_use.summary().as_deref(),
+            Some("Landing 14 · Departing 16")
+        );
+
+        // All but the repeated sighting have expired.
+        watch.observe([], start + TimeDelta::minutes(WINDOW_MINUTES));
+        let in_use = watch.in_use();
+        assert_eq!(in_use.landing[0].aircraft, 1);
+        assert!(in_use.departure.is_empty());
+        assert_eq!(in_use.summary().as_deref(), Some("Landing 14"));
+        watch.observe([], start + TimeDelta::hours(1));
+        assert_eq!(watch.in_use().summary(), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Time-of-day windows during which the display shows something other than live traffic.
+
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// A static night screen saying when live traffic resumes.
+    Night,
+    /// The flights seen over the last day.
+    Summary,
+    /// Keep showing the last live image.
+    Hold,
+}
+
+impl DisplayMode {
+    /// Why the device is told to sleep, for refresh hints.
+    pub fn reason(self) -> &'static str {
+        match self {
+            DisplayMode::Night => "night screen",
+            DisplayMode::Summary => "daily summary",
+            DisplayMode::Hold => "holding last image",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleWindow {
+    /// Local time the window starts, inclusive.
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime,
+    /// Local time the window ends, exclusive. Before `start` for windows spanning midnight.
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: NaiveTime,
+    pub mode: DisplayMode,
+}
+
+impl ScheduleWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Parses `HH:MM`.
+fn deserialize_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&text, "%H:%M")
+        .map_err(|_| serde::de::Error::custom(format!("invalid time '{}', expected HH:MM", text)))
+}
+
+/// The window covering the local `time`, the first listed if several overlap.
+pub fn active_window(schedule: &[ScheduleWindow], time: NaiveTime) -> Option<&ScheduleWindow> {
+    schedule.iter().find(|w| w.contains(time))
+}
+
+/// When live traffic is shown again, following back-to-back windows. `None` if no window is
+/// active at `now`, or if the windows cover the whole day.
+pub fn live_again(schedule: &[ScheduleWindow], now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let mut window = active_window(schedule, now.time())?;
+    let mut t = now.naive_local();
+
+    for _ in 0..schedule.len() {
+        let end = t.date().and_time(window.end);
+        t = if end > t {
+            end
+        } else {
+            end + TimeDelta::days(1)
+        };
+        match active_window(schedule, t.time()) {
+            Some(next) => window = next,
+            None => return Some(resolve_local(now.timezone(), t)),
+        }
+    }
+    None
+}
+
+/// The instant the clocks show `local`.
+pub fn resolve_local(tz: Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    // A wall time skipped by the switch to daylight saving time happens an hour later.
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| tz.from_utc_datetime(&local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Zurich;
+
+    fn window(start: &str, end: &str, mode: DisplayMode) -> ScheduleWindow {
+        ScheduleWindow {
+            start: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            end: NaiveTime::parse_from_str(end, "%H:%M").unwrap(),
+            mode,
+        }
+    }
+
+    fn zurich(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        Zurich.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_active_window() {
+        let schedule = vec![
+            window("22:00", "00:00", DisplayMode::Summary),
+            window("00:00", "06:00", DisplayMode::Night),
+        ];
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(active_window(&schedule, at(21, 59)).map(|w| w.mode), None);
+        assert_eq!(
+            active_window(&schedule, at(23, 30)).map(|w| w.mode),
+            Some(DisplayMode::Summary)
+        );
+        assert_eq!(
+            active_window(&schedule, at(0, 0)).map(|w| w.mode),
+            Some(DisplayMode::Night)
+        );
+        assert_eq!(active_window(&schedule, at(6, 0)).map(|w| w.mode), None);
+    }
+
+    #[test]
+    fn test_live_again() {
+        let schedule = vec![
+            window("22:00", "00:00", DisplayMode::Summary),
+            window("00:00", "06:00", DisplayMode::Night),
+        ];
+        assert_eq!(live_again(&schedule, zurich(2026, 6, 1, 12, 0)), None);
+        // Back-to-back windows are followed through midnight.
+        assert_eq!(
+            live_again(&schedule, zurich(2026, 6, 1, 23, 0)),
+            Some(zurich(2026, 6, 2, 6, 0))
+        );
+
+        // The clocks go forward at 02:00 on 29 March 2026, so the night is an hour shorter.
+        let start = zurich(2026, 3, 29, 1, 0);
+        let resume = live_again(&schedule, start).unwrap();
+        assert_eq!(resume, zurich(2026, 3, 29, 6, 0));
+        assert_eq!((resume - start).num_hours(), 4);
+
+        // A window ending in the skipped hour ends when the clocks show 03:30.
+        let schedule = vec![window("01:00", "02:30", DisplayMode::Hold)];
+        assert_eq!(
+            live_again(&schedule, zurich(2026, 3, 29, 1, 0)),
+            Some(zurich(2026, 3, 29, 3, 30))
+        );
+
+        let always = vec![
+            window("06:00", "18:00", DisplayMode::Hold),
+            window("18:00", "06:00", DisplayMode::Night),
+        ];
+        assert_eq!(live_again(&always, zurich(2026, 6, 1, 12, 0)), None);
+    }
+}
@@ -0,0 +1,330 @@
+//! Screens shown instead of a flight.
+
+use chrono::{DateTime, NaiveTime, TimeDelta, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::SvgLayers;
+use crate::geo::compass_point;
+use crate::history::History;
+use crate::schedule::resolve_local;
+use crate::svg::SvgWriter;
+use crate::text_fit::TextFitter;
+use crate::traffic::Aircraft;
+
+/// Most recent flights listed on the summary screen.
+const SUMMARY_FLIGHTS: usize = 12;
+
+/// Widest value in an idle screen info block, which are 500 pixels apart.
+const INFO_VALUE_WIDTH: f32 = 460.0;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleConfig {
+    /// Show the nearest aircraft outside the display radius.
+    pub show_nearest: bool,
+    /// Show the runways in use.
+    pub show_runways: bool,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            show_nearest: true,
+            show_runways: false,
+        }
+    }
+}
+
+const FONT_FAMILY: &str = "Google Sans, sans-serif";
+
+fn white_background() -> String {
+    let mut svg = SvgWriter::new();
+    svg.element("rect")
+        .attr("width", 1600)
+        .attr("height", 1200)
+        .attr("fill", "white")
+        .empty();
+    svg.finish()
+}
+
+/// Text centred on `x`.
+fn text(svg: &mut SvgWriter, x: usize, y: usize, size: f32, bold: bool, content: &str) {
+    let element = svg
+        .element("text")
+        .attr("x", x)
+        .attr("y", y)
+        .attr("font-family", FONT_FAMILY)
+        .attr("font-size", size)
+        .attr("text-anchor", "middle")
+        .attr("fill", "#000000");
+    if bold {
+        element.attr("font-weight", "bold").text(content);
+    } else {
+        element.text(content);
+    }
+}
+
+/// One labelled value in the idle screen's bottom row, centred on `x`.
+fn info_block(
+    svg: &mut SvgWriter,
+    fitter: &TextFitter,
+    x: usize,
+    label: &str,
+    value: &str,
+    detail: &str,
+) {
+    svg.element("g")
+        .attr("transform", format!("translate({}, 880)", x))
+        .open();
+    text(svg, 0, 0, 40.0, false, label);
+    let value = fitter.fit(value, INFO_VALUE_WIDTH, 80.0, 50.0, true);
+    text(svg, 0, 95, value.size, true, &value.text);
+    let detail = fitter.fit(detail, INFO_VALUE_WIDTH, 40.0, 30.0, false);
+    text(svg, 0, 155, detail.size, false, &detail.text);
+    svg.close("g");
+}
+
+/// How long ago something happened, e.g. `12 min ago`.
+fn format_ago(elapsed: TimeDelta) -> String {
+    match elapsed.num_minutes() {
+        m if m < 1 => "just now".to_string(),
+        m if m < 60 => format!("{} min ago", m),
+        m if m < 24 * 60 => format!("{} h ago", m / 60),
+        m => format!("{} days ago", m / (24 * 60)),
+    }
+}
+
+/// Shown while no aircraft is in range: the time, the last aircraft seen, the number of flights
+/// today and, if given, the runways in use and the nearest aircraft outside the radius.
+pub fn render_idle_svg(
+    now: DateTime<Tz>,
+    history: &History,
+    nearest: Option<&Aircraft>,
+    runways: Option<&str>,
+    fitter: &TextFitter,
+) -> SvgLayers {
+    let midnight = resolve_local(now.timezone(), now.date_naive().and_time(NaiveTime::MIN));
+    let today = history.since(midnight.to_utc()).count();
+
+    let mut svg = SvgWriter::new();
+    text(
+        &mut svg,
+        800,
+        400,
+        260.0,
+        true,
+        &now.format("%H:%M").to_string(),
+    );
+    text(
+        &mut svg,
+        800,
+        490,
+        55.0,
+        false,
+        &now.format("%A %-d %B").to_string(),
+    );
+    text(&mut svg, 800, 620, 45.0, false, "No aircraft in range");
+    if let Some(runways) = runways {
+        text(&mut svg, 800, 700, 45.0, true, runways);
+    }
+
+    let (last, ago) = match history.last() {
+        Some(sighting) => (
+            sighting.display_name(),
+            format_ago(now.to_utc() - sighting.last_seen),
+        ),
+        None => ("---", "none yet".to_string()),
+    };
+    info_block(&mut svg, fitter, 300, "LAST SEEN", last, &ago);
+    info_block(
+        &mut svg,
+        fitter,
+        800,
+        "TODAY",
+        &today.to_string(),
+        if today == 1 { "flight" } else { "flights" },
+    );
+    if let Some(aircraft) = nearest {
+        let name = if aircraft.callsign.is_empty() {
+            &aircraft.icao24
+        } else {
+            &aircraft.callsign
+        };
+        info_block(
+            &mut svg,
+            fitter,
+            1300,
+            "NEAREST",
+            name,
+            &format!(
+                "{:.1} km {} ({:03.0}°)",
+                aircraft.distance,
+                compass_point(aircraft.bearing),
+                aircraft.bearing
+            ),
+        );
+    }
+
+    SvgLayers {
+        background: white_background(),
+        overlay: svg.finish(),
+    }
+}
+
+/// Static screen for quiet hours, saying when live traffic resumes.
+pub fn render_night_svg(resume: Option<DateTime<Tz>>) -> SvgLayers {
+    let mut svg = SvgWriter::new();
+    text(&mut svg, 800, 600, 140.0, true, "Good night");
+    if let Some(resume) = resume {
+        let line = format!("Live traffic resumes at {}", resume.format("%H:%M"));
+        text(&mut svg, 800, 720, 50.0, false, &line);
+    }
+
+    SvgLayers {
+        background: white_background(),
+        overlay: svg.finish(),
+    }
+}
+
+/// The flights seen over the 24 hours before `now`.
+pub fn render_summary_svg(history: &History, now: DateTime<Tz>) -> SvgLayers {
+    let sightings: Vec<_> = history.since(now.to_utc() - TimeDelta::days(1)).collect();
+
+    let mut per_hour = [0usize; 24];
+    for sighting in &sightings {
+        per_hour[sighting.first_seen.with_timezone(&now.timezone()).hour() as usize] += 1;
+    }
+    let busiest = (0..24).max_by_key(|&h| (per_hour[h], std::cmp::Reverse(h)));
+
+    let mut svg = SvgWriter::new();
+    text(&mut svg, 800, 150, 60.0, false, "Last 24 hours");
+    text(
+        &mut svg,
+        800,
+        400,
+        220.0,
+        true,
+        &sightings.len().to_string(),
+    );
+    let unit = if sightings.len() == 1 {
+        "flight"
+    } else {
+        "flights"
+    };
+    text(&mut svg, 800, 480, 60.0, false, unit);
+
+    if let Some(hour) = busiest.filter(|&h| per_hour[h] > 0) {
+        let line = format!(
+            "Busiest hour {:02}:00–{:02}:00 ({})",
+            hour,
+            (hour + 1) % 24,
+            per_hour[hour]
+        );
+        text(&mut svg, 800, 580, 45.0, false, &line);
+    }
+
+    // Most recent first, in three columns.
+    for (i, sighting) in sightings.iter().rev().take(SUMMARY_FLIGHTS).enumerate() {
+        let x = 300 + (i % 3) * 500;
+        let y = 720 + (i / 3) * 110;
+        let seen = sighting.first_seen.with_timezone(&now.timezone());
+        text(&mut svg, x, y, 55.0, true, sighting.display_name());
+        text(
+            &mut svg,
+            x,
+            y + 45,
+            35.0,
+            false,
+            &seen.format("%H:%M").to_string(),
+        );
+    }
+
+    SvgLayers {
+        background: white_background(),
+        overlay: svg.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_fit::embedded;
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Zurich;
+    use proptest::prelude::*;
+
+    fn aircraft(icao24: String, callsign: String, distance: f64, bearing: f64) -> Aircraft {
+        Aircraft {
+            callsign,
+            bearing,
+            ..Aircraft::test(&icao24, distance)
+        }
+    }
+
+    #[test]
+    fn test_render_screens() {
+        let now = Zurich.with_ymd_and_hms(2026, 6, 1, 23, 0, 0).unwrap();
+        let mut history = History::default();
+        for (i, hours_ago) in [30, 5, 4, 4].into_iter().enumerate() {
+            let aircraft = aircraft(format!("4b180{}", i), format!("SWR{}", i), 1.0, 0.0);
+            history.record([&aircraft], now.to_utc() - TimeDelta::hours(hours_ago));
+        }
+
+        let svg = render_summary_svg(&history, now).to_svg();
+        assert!(svg.contains(">3</text>"));
+        assert!(svg.contains("Busiest hour 19:00–20:00 (2)"));
+        assert!(svg.contains(">SWR3</text>"));
+        assert!(!svg.contains(">SWR0</text>"));
+
+        let svg = render_idle_svg(now, &history, None, None, &embedded()).to_svg();
+        assert!(svg.contains(">23:00</text>"));
+        assert!(svg.contains(">SWR3</text>"));
+        assert!(svg.contains(">4 h ago</text>"));
+        // The one 30 hours ago was yesterday.
+        assert!(svg.contains(">3</text>"));
+        assert!(!svg.contains("NEAREST"));
+
+        let night = render_night_svg(Some(now + TimeDelta::hours(7))).to_svg();
+        assert!(night.contains("resumes at 06:00"));
+    }
+
+    #[test]
+    fn test_render_idle_svg_nearest() {
+        let now = Zurich.with_ymd_and_hms(2026, 6, 1, 9, 5, 0).unwrap();
+        let nearest = aircraft("4b1805".to_string(), String::new(), 12.34, 44.0);
+        let svg = render_idle_svg(
+            now,
+            &History::default(),
+            Some(&nearest),
+            Some("Landing 14 · Departing 16"),
+            &embedded(),
+        )
+        .to_svg();
+        assert!(svg.contains(">09:05</text>"));
+        assert!(svg.contains("Monday 1 June"));
+        assert!(svg.contains(">none yet</text>"));
+        assert!(svg.contains(">4b1805</text>"));
+        assert!(svg.contains(">12.3 km NE (044°)</text>"));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_any_callsign_parses(icao24 in any::<String>(), callsign in any::<String>()) {
+            let now = Zurich.with_ymd_and_hms(2026, 6, 1, 9, 5, 0).unwrap();
+            let aircraft = aircraft(icao24, callsign, 12.34, 44.0);
+            let mut history = History::default();
+            history.record([&aircraft], now.to_utc());
+
+            for layers in [
+                render_idle_svg(now, &history, Some(&aircraft), None, &embedded()),
+                render_summary_svg(&history, now),
+            ] {
+                let svg = layers.to_svg();
+                prop_assert!(usvg::Tree::from_str(&svg, &usvg::Options::default()).is_ok(), "{}", svg);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Writing SVG markup without letting text from outside break it.
+//!
+//! Element and attribute names are static strings in the code; every attribute value and text
+//! node is escaped.
+
+use std::borrow::Cow;
+use std::fmt::{Display, Write};
+
+/// Whether `c` may appear in an XML 1.0 document at all, escaped or not.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// Escapes `text` for an attribute value or text node, dropping characters XML can't contain.
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text
+        .chars()
+        .any(|c| matches!(c, '&' | '<' | '>' | '"' | '\'') || !is_xml_char(c))
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if is_xml_char(c) => out.push(c),
+            _ => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[derive(Default)]
+pub struct SvgWriter {
+    out: String,
+}
+
+impl SvgWriter {
+    pub fn new() -> SvgWriter {
+        SvgWriter::default()
+    }
+
+    /// Starts an element, to be finished with `text`, `empty` or `open`.
+    pub fn element(&mut self, name: &'static str) -> ElementWriter<'_> {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str("  <");
+        self.out.push_str(name);
+        ElementWriter {
+            out: &mut self.out,
+            name,
+        }
+    }
+
+    /// Closes an element started with `open`.
+    pub fn close(&mut self, name: &'static str) {
+        self.out.push_str("\n  </");
+        self.out.push_str(name);
+        self.out.push('>');
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+pub struct ElementWriter<'a> {
+    out: &'a mut String,
+    name: &'static str,
+}
+
+impl ElementWriter<'_> {
+    pub fn attr(self, name: &'static str, value: impl Display) -> Self {
+        let value = value.to_string();
+        let _ = write!(self.out, " {}='{}'", name, escape(&value));
+        self
+    }
+
+    /// Ends the element with `content` as its text.
+    pub fn text(self, content: &str) {
+        let _ = write!(self.out, ">{}</{}>", escape(content), self.name);
+    }
+
+    /// Ends the element without content.
+    pub fn empty(self) {
+        self.out.push_str(" />");
+    }
+
+    /// Ends the start tag, for children to follow until `SvgWriter::close`.
+    pub fn open(self) {
+        self.out.push('>');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("SWR123"), Cow::Borrowed("SWR123"));
+        assert_eq!(
+            escape("Saint-Denis & Réunion <'\">"),
+            "Saint-Denis &amp; Réunion &lt;&apos;&quot;&gt;"
+        );
+        assert_eq!(escape("a\u{0}b\u{1b}c\u{FFFE}"), "abc");
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut svg = SvgWriter::new();
+        svg.element("g")
+            .attr("transform", "translate(0, 10)")
+            .open();
+        svg.element("text")
+            .attr("x", 5)
+            .attr("font-family", "Google Sans, sans-serif")
+            .text("A & B");
+        svg.close("g");
+        svg.element("rect").attr("width", 1.5).empty();
+        assert_eq!(
+            svg.finish(),
+            "  <g transform='translate(0, 10)'>\n  <text x='5' font-family='Google Sans, sans-serif'>A &amp; B</text>\n  </g>\n  <rect width='1.5' />"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_any_text_parses(text in any::<String>(), attr in any::<String>()) {
+            let mut svg = SvgWriter::new();
+            svg.element("text").attr("id", &attr).text(&text);
+            let doc = format!("<svg xmlns='http://www.w3.org/2000/svg'>{}</svg>", svg.finish());
+
+            let parsed = roxmltree::Document::parse(&doc).unwrap();
+            let element = parsed.root_element().first_element_child().unwrap();
+            let kept = |s: &str| -> String { s.chars().filter(|&c| is_xml_char(c)).collect() };
+            // Parsers normalise line endings, and whitespace in attributes.
+            let text = kept(&text).replace("\r\n", "\n").replace('\r', "\n");
+            prop_assert_eq!(element.text().unwrap_or(""), text);
+            let attr = kept(&attr).replace("\r\n", " ").replace(['\t', '\n', '\r'], " ");
+            prop_assert_eq!(element.attribute("id").unwrap(), attr);
+        }
+    }
+}
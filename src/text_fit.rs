@@ -0,0 +1,158 @@
+//! Fitting text into a box by measuring it with the embedded font.
+
+use serde::Serialize;
+use std::sync::Arc;
+use ttf_parser::{Face, Tag};
+use usvg::fontdb::{self, Family, Query, Weight};
+
+/// The family every screen is set in.
+pub const FAMILY: &str = "Google Sans";
+
+const ELLIPSIS: char = '…';
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fitted {
+    pub text: String,
+    /// Font size in pixels.
+    pub size: f32,
+}
+
+/// Measures text with the fonts the SVGs are rendered with.
+#[derive(Clone)]
+pub struct TextFitter {
+    fontdb: Arc<fontdb::Database>,
+}
+
+impl TextFitter {
+    pub fn new(fontdb: Arc<fontdb::Database>) -> TextFitter {
+        TextFitter { fontdb }
+    }
+
+    /// Width of `text` in pixels at `size`, ignoring kerning. `None` without the font.
+    pub fn width(&self, text: &str, size: f32, bold: bool) -> Option<f32> {
+        let weight = if bold { Weight::BOLD } else { Weight::NORMAL };
+        let id = self.fontdb.query(&Query {
+            families: &[Family::Name(FAMILY)],
+            weight,
+            ..Default::default()
+        })?;
+
+        self.fontdb
+            .with_face_data(id, |data, index| {
+                let mut face = Face::parse(data, index).ok()?;
+                if face.is_variable() {
+                    face.set_variation(Tag::from_bytes(b"wght"), weight.0 as f32);
+                }
+                let fallback = face.units_per_em() / 2;
+                let units: u32 = text
+                    .chars()
+                    .map(|c| {
+                        face.glyph_index(c)
+                            .and_then(|glyph| face.glyph_hor_advance(glyph))
+                            .unwrap_or(fallback) as u32
+                    })
+                    .sum();
+                Some(units as f32 * size / face.units_per_em() as f32)
+            })
+            .flatten()
+    }
+
+    /// Shrinks `text` from `size` towards `min_size` until it is at most `max_width` wide, then
+    /// cuts it short with an ellipsis. Text that can't be measured is returned as is.
+    pub fn fit(&self, text: &str, max_width: f32, size: f32, min_size: f32, bold: bool) -> Fitted {
+        let unchanged = Fitted {
+            text: text.to_string(),
+            size,
+        };
+        let Some(width) = self.width(text, size, bold) else {
+            return unchanged;
+        };
+        if width <= max_width {
+            return unchanged;
+        }
+
+        // Width scales linearly with the size; whole pixels keep the markup tidy.
+        let scaled = (size * max_width / width).floor();
+        if scaled >= min_size {
+            return Fitted {
+                text: text.to_string(),
+                size: scaled,
+            };
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+        while !chars.is_empty() {
+            chars.pop();
+            let candidate: String = chars.iter().collect::<String>().trim_end().to_string();
+            let candidate = format!("{}{}", candidate, ELLIPSIS);
+            if self
+                .width(&candidate, min_size, bold)
+                .is_some_and(|w| w <= max_width)
+            {
+                return Fitted {
+                    text: candidate,
+                    size: min_size,
+                };
+            }
+        }
+        Fitted {
+            text: ELLIPSIS.to_string(),
+            size: min_size,
+        }
+    }
+}
+
+/// A fitter with just the embedded font, for tests.
+#[cfg(test)]
+pub fn embedded() -> TextFitter {
+    let mut fontdb = fontdb::Database::new();
+    fontdb.load_font_data(crate::FONT_DATA.to_vec());
+    TextFitter::new(Arc::new(fontdb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        let fitter = embedded();
+        let short = fitter.width("A321", 70.0, false).unwrap();
+        let long = fitter.width("Airbus A321-271NX", 70.0, false).unwrap();
+        assert!(short > 0.0 && long > 3.0 * short);
+        let half = fitter.width("Airbus A321-271NX", 35.0, false).unwrap();
+        assert!((long - 2.0 * half).abs() < 0.01);
+        assert!(
+            TextFitter::new(Arc::new(fontdb::Database::new()))
+                .width("A321", 70.0, false)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_fit() {
+        let fitter = embedded();
+
+        let fitted = fitter.fit("ZRH", 400.0, 90.0, 40.0, true);
+        assert_eq!(
+            fitted,
+            Fitted {
+                text: "ZRH".to_string(),
+                size: 90.0
+            }
+        );
+
+        let fitted = fitter.fit("Airbus A321-271NX", 400.0, 70.0, 30.0, true);
+        assert!(fitted.size < 70.0 && fitted.size >= 30.0);
+        assert_eq!(fitted.text, "Airbus A321-271NX");
+        assert!(fitter.width(&fitted.text, fitted.size, true).unwrap() <= 400.0);
+
+        let fitted = fitter.fit("Santa Cruz de Tenerife", 300.0, 70.0, 50.0, false);
+        assert_eq!(fitted.size, 50.0);
+        assert!(fitted.text.starts_with("Santa Cru"), "{:?}", fitted);
+        assert!(fitted.text.ends_with('…'));
+        assert!(fitter.width(&fitted.text, 50.0, false).unwrap() <= 300.0);
+
+        assert_eq!(fitter.fit("WWWW", 1.0, 70.0, 50.0, false).text, "…");
+    }
+}
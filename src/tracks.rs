@@ -0,0 +1,149 @@
+//! Recent positions of each aircraft from successive polls, for drawing where it came from and
+//! telling whether it is climbing or descending.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::{HashMap, VecDeque};
+
+use crate::map::LatLon;
+use crate::traffic::Aircraft;
+
+/// Positions kept per aircraft, however often traffic is polled.
+const MAX_POINTS: usize = 120;
+/// The shortest track a climb rate is worked out from, as altitudes are only reported in steps.
+const MIN_CLIMB_SECONDS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrackPoint {
+    position: LatLon,
+    /// Barometric altitude, or geometric if there is none, in meters.
+    altitude: Option<f64>,
+    time: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct Tracks {
+    /// How long positions are kept.
+    max_age: TimeDelta,
+    /// Oldest first, per icao24.
+    points: HashMap<String, VecDeque<TrackPoint>>,
+}
+
+impl Tracks {
+    pub fn new(max_age: TimeDelta) -> Tracks {
+        Tracks {
+            max_age,
+            points: HashMap::new(),
+        }
+    }
+
+    /// Records the positions of the airborne aircraft polled at `now`, and forgets positions
+    /// older than the maximum age.
+    pub fn record<'a>(
+        &mut self,
+        traffic: impl IntoIterator<Item = &'a Aircraft>,
+        now: DateTime<Utc>,
+    ) {
+        for aircraft in traffic.into_iter().filter(|a| !a.on_ground) {
+            let track = self.points.entry(aircraft.icao24.clone()).or_default();
+            let position = (aircraft.latitude, aircraft.longitude);
+            // OpenSky repeats the last position of aircraft it hasn't heard from lately.
+            if track.back().is_some_and(|last| last.position == position) {
+                continue;
+            }
+            if track.len() == MAX_POINTS {
+                track.pop_front();
+            }
+            track.push_back(TrackPoint {
+                position,
+                altitude: aircraft.baro_altitude.or(aircraft.geo_altitude),
+                time: now,
+            });
+        }
+
+        let cutoff = now - self.max_age;
+        self.points.retain(|_, track| {
+            while track.front().is_some_and(|p| p.time <= cutoff) {
+                track.pop_front();
+            }
+            !track.is_empty()
+        });
+    }
+
+    /// The recorded positions of `icao24`, oldest first.
+    pub fn trail(&self, icao24: &str) -> Vec<LatLon> {
+        self.points
+            .get(icao24)
+            .map(|track| track.iter().map(|p| p.position).collect())
+            .unwrap_or_default()
+    }
+
+    /// The average climb rate of `icao24` over its recorded track in m/s, negative when
+    /// descending, if it has been tracked long enough.
+    pub fn climb_rate(&self, icao24: &str) -> Option<f64> {
+        let track = self.points.get(icao24)?;
+        let with_altitude = |p: &TrackPoint| Some((p.altitude?, p.time));
+        let (first, start) = track.iter().find_map(with_altitude)?;
+        let (last, end) = track.iter().rev().find_map(with_altitude)?;
+        let seconds = (end - start).num_seconds();
+        (seconds >= MIN_CLIMB_SECONDS).then(|| (last - first) / seconds as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aircraft(icao24: &str, latitude: f64, on_ground: bool) -> Aircraft {
+        Aircraft {
+            latitude,
+            longitude: 8.4,
+            on_ground,
+            ..Aircraft::test(icao24, 1.0)
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let start = Utc::now();
+        let mut tracks = Tracks::new(TimeDelta::minutes(5));
+        for minute in 0..8 {
+            let now = start + TimeDelta::minutes(minute);
+            let moving = aircraft("4b1805", 47.0 + minute as f64 / 100.0, false);
+            let parked = aircraft("4b1806", 47.0, true);
+            let lost = aircraft("4b1807", 47.5, false);
+            tracks.record([&moving, &parked, &lost], now);
+        }
+
+        // Minutes 3 to 7, the older ones have expired.
+        let trail = tracks.trail("4b1805");
+        assert_eq!(trail.len(), 5);
+        assert_eq!(trail[0], (47.03, 8.4));
+        assert_eq!(trail[4], (47.07, 8.4));
+        assert!(tracks.trail("4b1806").is_empty());
+        // Repeated positions aren't movement.
+        assert_eq!(tracks.trail("4b1807"), vec![(47.5, 8.4)]);
+        assert!(tracks.trail("unknown").is_empty());
+
+        let mut tracks = Tracks::new(TimeDelta::hours(24));
+        for i in 0..(MAX_POINTS + 10) {
+            tracks.record([&aircraft("4b1805", i as f64 / 1000.0, false)], start);
+        }
+        assert_eq!(tracks.trail("4b1805").len(), MAX_POINTS);
+    }
+
+    #[test]
+    fn test_climb_rate() {
+        let start = Utc::now();
+        let mut tracks = Tracks::new(TimeDelta::minutes(5));
+        for minute in 0..3 {
+            let mut climbing = aircraft("4b1805", 47.0 + minute as f64 / 100.0, false);
+            climbing.baro_altitude = Some(500.0 + 600.0 * minute as f64);
+            tracks.record([&climbing], start + TimeDelta::minutes(minute));
+        }
+        assert_eq!(tracks.climb_rate("4b1805"), Some(10.0));
+
+        tracks.record([&aircraft("4b1806", 47.0, false)], start);
+        assert_eq!(tracks.climb_rate("4b1806"), None);
+        assert_eq!(tracks.climb_rate("unknown"), None);
+    }
+}
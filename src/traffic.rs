@@ -0,0 +1,173 @@
+//! Aircraft state vectors from OpenSky.
+
+use serde_json::Value;
+
+use crate::geo::{bearing, haversine_distance, local_offset_km};
+
+/// One aircraft from an OpenSky `/states/all` response.
+#[derive(Debug, Clone)]
+pub struct Aircraft {
+    pub icao24: String,
+    pub callsign: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Barometric altitude in meters.
+    pub baro_altitude: Option<f64>,
+    pub on_ground: bool,
+    /// Ground speed in m/s.
+    pub velocity: Option<f64>,
+    /// Track over ground in degrees clockwise from north.
+    pub true_track: Option<f64>,
+    /// Climb rate in m/s, negative when descending.
+    pub vertical_rate: Option<f64>,
+    /// Geometric (GNSS) altitude in meters.
+    pub geo_altitude: Option<f64>,
+    /// Transponder code, e.g. `7000`.
+    pub squawk: Option<String>,
+    /// Distance from the observer in km.
+    pub distance: f64,
+    /// Bearing from the observer in degrees clockwise from north.
+    pub bearing: f64,
+}
+
+impl Aircraft {
+    /// Parses a state vector, see <https://openskynetwork.github.io/opensky-api/rest.html>.
+    /// Returns `None` for aircraft without a position.
+    pub fn from_state(state: &[Value], observer: (f64, f64)) -> Option<Aircraft> {
+        let field = |i: usize| state.get(i).unwrap_or(&Value::Null);
+        let latitude = field(6).as_f64()?;
+        let longitude = field(5).as_f64()?;
+
+        Some(Aircraft {
+            icao24: field(0).as_str().unwrap_or_default().to_string(),
+            callsign: field(1).as_str().unwrap_or_default().trim().to_string(),
+            latitude,
+            longitude,
+            baro_altitude: field(7).as_f64(),
+            on_ground: field(8).as_bool().unwrap_or(false),
+            velocity: field(9).as_f64(),
+            true_track: field(10).as_f64(),
+            vertical_rate: field(11).as_f64(),
+            geo_altitude: field(13).as_f64(),
+            squawk: field(14).as_str().map(str::to_string),
+            distance: haversine_distance(observer.0, observer.1, latitude, longitude),
+            bearing: bearing(observer.0, observer.1, latitude, longitude),
+        })
+    }
+
+    /// Seconds until the aircraft, holding its track and speed, comes within `radius_km` of the
+    /// observer. Zero if it already is, `None` if it won't.
+    pub fn seconds_until_within(&self, observer: (f64, f64), radius_km: f64) -> Option<f64> {
+        if self.distance <= radius_km {
+            return Some(0.0);
+        }
+        let speed = self.velocity? / 1000.0;
+        let track = self.true_track?.to_radians();
+        if self.on_ground || speed <= 0.0 {
+            return None;
+        }
+
+        // Solve |p + v t| = r for the first t > 0, with p the aircraft relative to the observer.
+        let (px, py) = local_offset_km(observer.0, observer.1, self.latitude, self.longitude);
+        let (vx, vy) = (speed * track.sin(), speed * track.cos());
+        let a = vx * vx + vy * vy;
+        let b = 2.0 * (px * vx + py * vy);
+        let c = px * px + py * py - radius_km * radius_km;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t = (-b - discriminant.sqrt()) / (2.0 * a);
+        (t > 0.0).then_some(t)
+    }
+}
+
+#[cfg(test)]
+impl Aircraft {
+    /// An airborne aircraft near Zurich with nothing else known, for tests to fill in.
+    pub fn test(icao24: &str, distance: f64) -> Aircraft {
+        Aircraft {
+            icao24: icao24.to_string(),
+            callsign: String::new(),
+            latitude: 47.4,
+            longitude: 8.5,
+            baro_altitude: Some(1000.0),
+            on_ground: false,
+            velocity: None,
+            true_track: None,
+            vertical_rate: None,
+            geo_altitude: None,
+            squawk: None,
+            distance,
+            bearing: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const OBSERVER: (f64, f64) = (47.4, 8.4);
+
+    fn state(lat: f64, lon: f64, velocity: f64, track: f64) -> Vec<Value> {
+        json!([
+            "4b1805",
+            "SWR123  ",
+            "Switzerland",
+            0,
+            0,
+            lon,
+            lat,
+            1500.0,
+            false,
+            velocity,
+            track,
+            -3.5,
+            null,
+            1550.0,
+            "1000",
+            false,
+            0
+        ])
+        .as_array()
+        .unwrap()
+        .clone()
+    }
+
+    #[test]
+    fn test_from_state() {
+        let aircraft = Aircraft::from_state(&state(47.45, 8.4, 80.0, 180.0), OBSERVER).unwrap();
+        assert_eq!(aircraft.icao24, "4b1805");
+        assert_eq!(aircraft.callsign, "SWR123");
+        assert_eq!(aircraft.baro_altitude, Some(1500.0));
+        assert_eq!(aircraft.geo_altitude, Some(1550.0));
+        assert_eq!(aircraft.vertical_rate, Some(-3.5));
+        assert_eq!(aircraft.squawk.as_deref(), Some("1000"));
+        assert!((aircraft.distance - 5.56).abs() < 0.05);
+        assert!(aircraft.bearing.abs() < 0.01);
+
+        let mut no_position = state(47.45, 8.4, 80.0, 180.0);
+        no_position[6] = Value::Null;
+        assert!(Aircraft::from_state(&no_position, OBSERVER).is_none());
+    }
+
+    #[test]
+    fn test_seconds_until_within() {
+        // 20 km north, flying south at 100 m/s: 12 km to go until the 8 km ring.
+        let inbound = Aircraft::from_state(&state(47.58, 8.4, 100.0, 180.0), OBSERVER).unwrap();
+        let t = inbound.seconds_until_within(OBSERVER, 8.0).unwrap();
+        assert!((t - 120.0).abs() < 3.0, "{}", t);
+
+        let outbound = Aircraft::from_state(&state(47.58, 8.4, 100.0, 0.0), OBSERVER).unwrap();
+        assert_eq!(outbound.seconds_until_within(OBSERVER, 8.0), None);
+
+        // Passing 15 km to the east never comes within 8 km.
+        let passing = Aircraft::from_state(&state(47.58, 8.6, 100.0, 180.0), OBSERVER).unwrap();
+        assert_eq!(passing.seconds_until_within(OBSERVER, 8.0), None);
+
+        let overhead = Aircraft::from_state(&state(47.41, 8.4, 100.0, 0.0), OBSERVER).unwrap();
+        assert_eq!(overhead.seconds_until_within(OBSERVER, 8.0), Some(0.0));
+    }
+}
@@ -0,0 +1,153 @@
+//! Units for altitudes and speeds on screen. State vectors are in meters and m/s.
+
+use serde::Deserialize;
+
+const FEET_PER_METER: f64 = 3.28084;
+const KNOTS_PER_MPS: f64 = 1.94384;
+/// Vertical rates below this, in m/s, show as level flight.
+const LEVEL_MPS: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum AltitudeUnit {
+    /// Feet, with vertical rates in feet per minute.
+    #[default]
+    #[serde(rename = "ft")]
+    Feet,
+    /// Meters, with vertical rates in meters per second.
+    #[serde(rename = "m")]
+    Meters,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum SpeedUnit {
+    #[default]
+    #[serde(rename = "kt")]
+    Knots,
+    #[serde(rename = "km/h")]
+    KilometersPerHour,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitsConfig {
+    pub altitude: AltitudeUnit,
+    pub speed: SpeedUnit,
+}
+
+impl UnitsConfig {
+    pub fn altitude_unit(&self) -> &'static str {
+        match self.altitude {
+            AltitudeUnit::Feet => "ft",
+            AltitudeUnit::Meters => "m",
+        }
+    }
+
+    pub fn speed_unit(&self) -> &'static str {
+        match self.speed {
+            SpeedUnit::Knots => "kt",
+            SpeedUnit::KilometersPerHour => "km/h",
+        }
+    }
+
+    pub fn vertical_rate_unit(&self) -> &'static str {
+        match self.altitude {
+            AltitudeUnit::Feet => "ft/min",
+            AltitudeUnit::Meters => "m/s",
+        }
+    }
+
+    /// `meters` in the altitude unit, rounded to a whole number.
+    pub fn altitude(&self, meters: f64) -> i64 {
+        match self.altitude {
+            AltitudeUnit::Feet => (meters * FEET_PER_METER).round() as i64,
+            AltitudeUnit::Meters => meters.round() as i64,
+        }
+    }
+
+    /// A ground speed of `mps` in the speed unit, rounded to a whole number.
+    pub fn speed(&self, mps: f64) -> i64 {
+        match self.speed {
+            SpeedUnit::Knots => (mps * KNOTS_PER_MPS).round() as i64,
+            SpeedUnit::KilometersPerHour => (mps * 3.6).round() as i64,
+        }
+    }
+
+    /// A vertical rate of `mps` in the vertical rate unit, feet rounded to 100 ft/min and meters
+    /// to 0.1 m/s.
+    pub fn vertical_rate(&self, mps: f64) -> f64 {
+        match self.altitude {
+            AltitudeUnit::Feet => (mps * FEET_PER_METER * 60.0 / 100.0).round() * 100.0,
+            AltitudeUnit::Meters => (mps * 10.0).round() / 10.0,
+        }
+    }
+
+    /// E.g. `12,300 ft`.
+    pub fn format_altitude(&self, meters: f64) -> String {
+        format!(
+            "{} {}",
+            group_thousands(self.altitude(meters)),
+            self.altitude_unit()
+        )
+    }
+
+    /// E.g. `420 kt`.
+    pub fn format_speed(&self, mps: f64) -> String {
+        format!("{} {}", group_thousands(self.speed(mps)), self.speed_unit())
+    }
+
+    /// E.g. `+1,200 ft/min`, or `level`.
+    pub fn format_vertical_rate(&self, mps: f64) -> String {
+        if mps.abs() < LEVEL_MPS {
+            return "level".to_string();
+        }
+        let rate = self.vertical_rate(mps);
+        let sign = if mps > 0.0 { '+' } else { '-' };
+        let magnitude = match self.altitude {
+            AltitudeUnit::Feet => group_thousands(rate.abs() as i64),
+            AltitudeUnit::Meters => format!("{:.1}", rate.abs()),
+        };
+        format!("{}{} {}", sign, magnitude, self.vertical_rate_unit())
+    }
+}
+
+/// `12300` as `12,300`.
+fn group_thousands(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let imperial = UnitsConfig::default();
+        assert_eq!(imperial.format_altitude(3749.04), "12,300 ft");
+        assert_eq!(imperial.format_altitude(-10.0), "-33 ft");
+        assert_eq!(imperial.format_speed(216.0), "420 kt");
+        assert_eq!(imperial.format_vertical_rate(6.1), "+1,200 ft/min");
+        assert_eq!(imperial.format_vertical_rate(-5.0), "-1,000 ft/min");
+        assert_eq!(imperial.format_vertical_rate(0.3), "level");
+
+        let metric = UnitsConfig {
+            altitude: AltitudeUnit::Meters,
+            speed: SpeedUnit::KilometersPerHour,
+        };
+        assert_eq!(metric.format_altitude(3749.04), "3,749 m");
+        assert_eq!(metric.format_speed(216.0), "778 km/h");
+        assert_eq!(metric.format_vertical_rate(-6.14), "-6.1 m/s");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1_000_000), "1,000,000");
+    }
+}